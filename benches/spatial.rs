@@ -0,0 +1,54 @@
+#[macro_use]
+extern crate criterion;
+extern crate rand;
+extern crate solar_rustlib;
+
+use criterion::Criterion;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use solar_rustlib::util::SpatialIndex;
+
+const ENTRIES_NUMBER: usize = 100000;
+const AREA_SIZE: f64 = 20000.0;
+
+fn populated_index() -> SpatialIndex<usize> {
+    let mut rng = XorShiftRng::from_seed([7, 11, 13, 17]);
+    let mut index = SpatialIndex::new(32.0);
+    for i in 0..ENTRIES_NUMBER {
+        index.insert((rng.gen_range(0.0, AREA_SIZE), rng.gen_range(0.0, AREA_SIZE)),
+                     rng.gen_range(1.0, 10.0),
+                     i);
+    }
+    index
+}
+
+fn bench_spatial_index(c: &mut Criterion) {
+    c.bench_function("spatial index : build 100k", |b| b.iter(populated_index));
+
+    let index = populated_index();
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    c.bench_function("spatial index : point query among 100k", move |b| {
+        b.iter(|| index.at_point((rng.gen_range(0.0, AREA_SIZE), rng.gen_range(0.0, AREA_SIZE))))
+    });
+
+    let index = populated_index();
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    c.bench_function("spatial index : radius query among 100k", move |b| {
+        b.iter(|| {
+            index.within_radius((rng.gen_range(0.0, AREA_SIZE), rng.gen_range(0.0, AREA_SIZE)),
+                                200.0)
+        })
+    });
+
+    let index = populated_index();
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+    c.bench_function("spatial index : rectangle query among 100k", move |b| {
+        b.iter(|| {
+            let (x, y) = (rng.gen_range(0.0, AREA_SIZE), rng.gen_range(0.0, AREA_SIZE));
+            index.within_rect((x, y), (x + 800.0, y + 450.0))
+        })
+    });
+}
+
+criterion_group!(benches, bench_spatial_index);
+criterion_main!(benches);
@@ -0,0 +1,313 @@
+//! Custom derive for solar-rust's 'TypeGenerator' trait.
+//!
+//! Deriving 'TypeGenerator' on a generator structure generates :
+//!
+//! - the builder setters of all its fields ;
+//! - a '<Generator>Samples' structure gathering one value sampled from each of
+//!   its 'Option<SamplerFunction<T, R>>' fields ;
+//! - the 'TypeGenerator' implementation, whose 'generate' method returns an
+//!   error for any sampler left unspecified and otherwise hands the samples
+//!   over to the generator's 'finish' method. The samplers marked as
+//!   'optional' are sampled as an 'Option' instead of being required.
+//!
+//! ```ignore
+//! #[derive(TypeGenerator)]
+//! #[generator(generated = "Asteroid", finish = "assemble")]
+//! pub struct AsteroidGenerator<R: Rng> {
+//!     radius: Option<SamplerFunction<f64, R>>,
+//!     #[generator(setter = "orbital_speed", optional)]
+//!     speed: Option<SamplerFunction<f64, R>>,
+//!     #[generator(default = "Distribution::Range { low: 0.0, high: 2.0 * PI }")]
+//!     angle: Option<SamplerFunction<f64, R>>,
+//!     #[generator(default = "Box::new(ColorGenerator::default())")]
+//!     color: BoxedColorGenerator<R>,
+//! }
+//!
+//! impl<R: Rng> AsteroidGenerator<R> {
+//!     fn assemble(&mut self,
+//!                 rng: &mut R,
+//!                 samples: AsteroidGeneratorSamples)
+//!                 -> Result<Asteroid, String> {
+//!         ...
+//!     }
+//! }
+//! ```
+//!
+//! The first type parameter of the structure is used as the random number
+//! generator type. The default of a sampler field is a 'Distribution'
+//! expression, while the default of any other field is its value : the latter
+//! must thus always be given one.
+//!
+//! The generated code refers to the library through absolute
+//! '::solar_rustlib::generator' paths : the derive can thus only be used from
+//! the crates depending on 'solar_rustlib' (e.g. the game or the integration
+//! tests), and not inside the library itself.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate syn;
+#[macro_use]
+extern crate quote;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use syn::{Data, DeriveInput, Expr, Fields, GenericArgument, Ident, LitStr, PathArguments, Type};
+
+/// A field of the derived generator.
+struct GeneratorField {
+    name: Ident,
+    ty: Type,
+    setter: Ident,
+    default: Option<Expr>,
+    /// The sampled type if the field is an 'Option<SamplerFunction<T, R>>'.
+    sampled: Option<Type>,
+    /// True if the sampler may be left unspecified.
+    optional: bool,
+}
+
+#[proc_macro_derive(TypeGenerator, attributes(generator))]
+pub fn derive_type_generator(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+
+    let mut generated: Option<Type> = None;
+    let mut finish: Option<Ident> = None;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("generator")) {
+        attr.parse_nested_meta(|meta| {
+            let value: LitStr = meta.value()?.parse()?;
+            if meta.path.is_ident("generated") {
+                generated = Some(value.parse()?);
+            } else if meta.path.is_ident("finish") {
+                finish = Some(value.parse()?);
+            } else {
+                return Err(meta.error("unknown generator attribute, expected 'generated' or \
+                                       'finish'"));
+            }
+            Ok(())
+        })?;
+    }
+    let generated = match generated {
+        Some(generated) => generated,
+        None => {
+            return Err(syn::Error::new_spanned(name,
+                                               "missing #[generator(generated = \"...\")] \
+                                                attribute"))
+        }
+    };
+    let finish = match finish {
+        Some(finish) => finish,
+        None => {
+            return Err(syn::Error::new_spanned(name,
+                                               "missing #[generator(finish = \"...\")] attribute"))
+        }
+    };
+
+    let rng = match input.generics.type_params().next() {
+        Some(param) => param.ident.clone(),
+        None => {
+            return Err(syn::Error::new_spanned(&input.generics,
+                                               "the generator must be generic over its random \
+                                                number generator type"))
+        }
+    };
+
+    let fields = match input.data {
+        Data::Struct(ref data) => {
+            match data.fields {
+                Fields::Named(ref fields) => &fields.named,
+                _ => {
+                    return Err(syn::Error::new_spanned(name,
+                                                       "TypeGenerator can only be derived for \
+                                                        structures with named fields"))
+                }
+            }
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(name,
+                                               "TypeGenerator can only be derived for structures"))
+        }
+    };
+
+    let mut generator_fields = Vec::new();
+    for field in fields {
+        let field_name = field.ident.clone().unwrap();
+        let mut setter = field_name.clone();
+        let mut default = None;
+        let mut optional = false;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("generator")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("optional") {
+                    optional = true;
+                    return Ok(());
+                }
+                let value: LitStr = meta.value()?.parse()?;
+                if meta.path.is_ident("setter") {
+                    setter = value.parse()?;
+                } else if meta.path.is_ident("default") {
+                    default = Some(value.parse()?);
+                } else {
+                    return Err(meta.error("unknown generator field attribute, expected \
+                                           'setter', 'default' or 'optional'"));
+                }
+                Ok(())
+            })?;
+        }
+
+        let sampled = sampled_type(&field.ty);
+        if sampled.is_none() && optional {
+            return Err(syn::Error::new_spanned(field, "only samplers can be optional"));
+        }
+        if sampled.is_none() && default.is_none() {
+            return Err(syn::Error::new_spanned(field,
+                                               "fields which are not samplers must be given a \
+                                                #[generator(default = \"...\")] value"));
+        }
+        generator_fields.push(GeneratorField {
+            name: field_name,
+            ty: field.ty.clone(),
+            setter: setter,
+            default: default,
+            sampled: sampled,
+            optional: optional,
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let samples_name = Ident::new(&format!("{}Samples", name), Span::call_site());
+
+    let setters = generator_fields.iter().map(|field| {
+        let (name, setter) = (&field.name, &field.setter);
+        match field.sampled {
+            Some(ref sampled) => {
+                quote! {
+                    #[allow(dead_code)]
+                    pub fn #setter(mut self,
+                                   value: ::solar_rustlib::generator::SamplerFunction<#sampled, #rng>)
+                                   -> Self {
+                        self.#name = Some(value); self
+                    }
+                }
+            }
+            None => {
+                let ty = &field.ty;
+                quote! {
+                    #[allow(dead_code)]
+                    pub fn #setter(mut self, value: #ty) -> Self {
+                        self.#name = value; self
+                    }
+                }
+            }
+        }
+    });
+
+    let defaults = generator_fields.iter().map(|field| {
+        let name = &field.name;
+        match (field.sampled.is_some(), field.default.as_ref()) {
+            (true, Some(default)) => quote! { #name: Some((#default).to_sampler()) },
+            (true, None) => quote! { #name: None },
+            (false, Some(default)) => quote! { #name: #default },
+            (false, None) => unreachable!(),
+        }
+    });
+
+    let sampler_fields: Vec<&GeneratorField> = generator_fields.iter()
+                                                               .filter(|field| {
+                                                                   field.sampled.is_some()
+                                                               })
+                                                               .collect();
+    let samples_fields = sampler_fields.iter().map(|field| {
+        let (name, sampled) = (&field.name, field.sampled.as_ref().unwrap());
+        if field.optional {
+            quote! { pub #name: Option<#sampled> }
+        } else {
+            quote! { pub #name: #sampled }
+        }
+    });
+    let samples_doc = format!("Values sampled by '{}' for each of its distributions.", name);
+    let sampling = sampler_fields.iter().map(|field| {
+        let name = &field.name;
+        if field.optional {
+            return quote! {
+                let #name = self.#name.as_ref().map(|sampler| sampler(rng));
+            };
+        }
+        let error = format!("{} : unspecified {} distribution", input.ident, name);
+        quote! {
+            let #name = match self.#name {
+                Some(ref sampler) => sampler(rng),
+                None => return Err(#error.into()),
+            };
+        }
+    });
+    let samples_names = sampler_fields.iter().map(|field| &field.name);
+
+    Ok(quote! {
+        #[doc = #samples_doc]
+        #[allow(dead_code)]
+        #vis struct #samples_name {
+            #(#samples_fields,)*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#setters)*
+        }
+
+        impl #impl_generics ::solar_rustlib::generator::TypeGenerator<#rng> for #name #ty_generics
+            #where_clause
+        {
+            type Generated = #generated;
+
+            fn default() -> Self {
+                #name {
+                    #(#defaults,)*
+                }
+            }
+
+            fn generate(&mut self, rng: &mut #rng) -> Result<#generated, String> {
+                #(#sampling)*
+                let samples = #samples_name {
+                    #(#samples_names: #samples_names,)*
+                };
+                self.#finish(rng, samples)
+            }
+        }
+    })
+}
+
+/// Return 'T' if the given type is 'Option<SamplerFunction<T, R>>'.
+fn sampled_type(ty: &Type) -> Option<Type> {
+    first_generic_argument(ty, "Option")
+        .and_then(|argument| first_generic_argument(&argument, "SamplerFunction"))
+}
+
+/// Return the first generic type argument of 'ty' if its last path segment
+/// is named 'segment_name'.
+fn first_generic_argument(ty: &Type, segment_name: &str) -> Option<Type> {
+    let path = match *ty {
+        Type::Path(ref type_path) => &type_path.path,
+        _ => return None,
+    };
+    let segment = match path.segments.last() {
+        Some(segment) if segment.ident == segment_name => segment,
+        _ => return None,
+    };
+    match segment.arguments {
+        PathArguments::AngleBracketed(ref arguments) => {
+            arguments.args.iter().filter_map(|argument| {
+                match *argument {
+                    GenericArgument::Type(ref ty) => Some(ty.clone()),
+                    _ => None,
+                }
+            }).next()
+        }
+        _ => None,
+    }
+}
@@ -53,6 +53,10 @@ impl SolarRustApp {
             } else if let Some(_) = e.resize_args() {
                 game.on_display_resize(window.draw_size());
                 ui.needs_redraw();
+            } else if let Some(button) = e.press_args() {
+                try!(game.on_button_press(button));
+            } else if let Some(position) = e.mouse_cursor_args() {
+                game.on_mouse_move(position);
             }
         }
 
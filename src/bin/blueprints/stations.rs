@@ -1,71 +1,79 @@
 use rand::Rng;
 
-use solar_rustlib::core::{ObjectRegister, ObjectType, ObjectVisuals};
+use solar_rustlib::core::{ObjectRegister, ObjectType, ObjectVisuals, ResourceRegistry,
+                          ResourceRole};
 #[macro_use]
 use solar_rustlib::util::*;
 use objects::*;
 
+/// Builds a station storing and producing the resources of the given
+/// registry. Its solar panels produce the resource of the 'SolarPower' role.
 pub struct TransfertStationBlueprint {
     orbit: Option<Orbit>,
+    resources: Option<ResourceRegistry>,
+    color: Option<(u8, u8, u8)>,
 }
 
 impl TransfertStationBlueprint {
     pub fn new() -> TransfertStationBlueprint {
-        TransfertStationBlueprint { orbit: None }
+        TransfertStationBlueprint {
+            orbit: None,
+            resources: None,
+            color: None,
+        }
     }
 
-    builder_setters!(options => { orbit { orbit: Orbit } }; others => {});
+    builder_setters!(
+        options => {
+            orbit { orbit: Orbit },
+            resources { resources: ResourceRegistry },
+            color { color: (u8, u8, u8) }
+        };
+        others => {}
+    );
 }
 
 impl<R: Rng> GameObjectBlueprint<R> for TransfertStationBlueprint {
     fn produce(&mut self, _: &mut R) -> Result<ObjectHandle, String> {
         use solar_rustlib::core::ObjectPropertyValue::*;
 
+        let resources = unwrap_or_err!(self.resources.as_ref(),
+                                       "TransfertStationBlueprint : missing resources parameter");
+        let solar_rate_key = try!(resources.with_role(ResourceRole::SolarPower)).rate_key();
+
         let mut reg = ObjectRegister::new();
         reg.add_property("level", Integer(1), "Current level.");
 
-        reg.add_property("minerals",
-                         Float(0.0),
-                         "Current amount of minerals in stock.");
-        reg.add_property("minerals_max",
-                         Float(1000.0),
-                         "Maximum amount of minerals that can be stored.");
-
-        reg.add_property("energy", Float(0.0), "Current amount of energy in stock.");
-        reg.add_property("energy_max",
-                         Float(1000.0),
-                         "Maximum amount of energy that can be stored.");
-        reg.add_property("energy_rate",
+        resources.fill_register(&mut reg);
+        reg.add_constant("solar_panels_output",
                          Float(10.0),
-                         "Amount of energy produced in situ by seconds.");
+                         "Amount of solar power produced by seconds under a solar flux of 1.");
+        reg.add_property("solar_flux",
+                         Float(0.0),
+                         "Flux received from the stars, in solar constants.");
+        reg.add_property("illumination",
+                         Float(1.0),
+                         "Fraction of the solar flux not hidden by the surrounding bodies.");
 
-        reg.set_display_name("minerals", "Minerals");
-        reg.set_display_name("minerals_max", "Minerals maximum stock");
-        reg.set_display_name("energy", "Energy");
-        reg.set_display_name("energy_max", "Energy maximum stock");
-        reg.set_display_name("energy_rate", "Energy production rate");
+        reg.set_display_name("solar_panels_output", "Solar panels output");
+        reg.set_display_name("solar_flux", "Solar flux");
+        reg.set_display_name("illumination", "Illumination");
 
-        let update_fn = Box::new(|reg: &mut ObjectRegister, dt: f64| {
-            let minerals_max = reg.get_float("minerals_max").unwrap().clone();
-            reg.get_float_mut("minerals").map(|v| {
-                if *v > minerals_max {
-                    *v = minerals_max
-                }
-            });
+        let resources = resources.clone();
+        let update_fn = Box::new(move |reg: &mut ObjectRegister, dt: f64| {
+            // the power comes from the stars' light, unless in the shadows
+            let solar_rate = reg.get_float("solar_panels_output").unwrap() *
+                             reg.get_float("solar_flux").unwrap() *
+                             reg.get_float("illumination").unwrap();
+            reg.get_float_mut(solar_rate_key.clone()).map(|v| *v = solar_rate);
 
-            let energy_max = reg.get_float("energy_max").unwrap().clone();
-            let energy_rate = reg.get_float("energy_rate").unwrap().clone();
-            reg.get_float_mut("energy").map(|v| {
-                *v += energy_rate * dt;
-                if *v > energy_max {
-                    *v = energy_max
-                }
-            });
+            resources.update_register(reg, dt);
         });
 
         let orbit = self.orbit.as_ref().unwrap_or(&Orbit::Fixed((0.0, 0.0))).clone();
+        let color = self.color.unwrap_or((100, 200, 200));
         Ok(DefaultObjectBuilder::with_visuals(ObjectType::Station,
-                                              ObjectVisuals::square(10.0, (100, 200, 200)))
+                                              ObjectVisuals::square(10.0, color))
                .orbit(orbit)
                .register(reg)
                .update_fn(update_fn)
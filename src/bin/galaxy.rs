@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use rand::{SeedableRng, StdRng};
+use piston_window::{Context, G2d, Ellipse, Line};
+
+use solar_rustlib::generator::{GalaxyMap, SystemNode};
+use solar_rustlib::util::{Vector, Vector2f};
+use objects::{GameSystem, ObjectHandle};
+use textures::TextureStore;
+
+const HYPERLANE_COLOR: [f32; 4] = [0.4, 0.4, 0.5, 1.0];
+const HYPERLANE_RADIUS: f64 = 1.0;
+const SYSTEM_RADIUS: f64 = 6.0;
+const SYSTEM_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+const SYSTEM_VISITED_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const SYSTEM_ACTIVE_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const SYSTEM_SELECTED_COLOR: [f32; 4] = [0.3, 0.8, 1.0, 1.0];
+
+/// Generate the content of a system of the galaxy, returning it along with
+/// its home object. Its generated textures are to be registered in the given
+/// store.
+pub type SystemGeneratorFn = Box<Fn(&SystemNode, &mut StdRng, &mut TextureStore)
+                                    -> Result<(GameSystem, ObjectHandle), String>>;
+
+/// The galaxy layer of the game, owning the star systems which are lazily
+/// generated from their seed the first time they are needed.
+/// Only the active system is simulated.
+pub struct Galaxy {
+    map: GalaxyMap,
+    /// The already generated systems with their home object, by index in the map.
+    systems: HashMap<usize, (GameSystem, ObjectHandle)>,
+    /// Index of the active system in the map.
+    active: usize,
+    generator: SystemGeneratorFn,
+    /// Distance between the galaxy's center and its farthest system.
+    extent: f64,
+}
+
+impl Galaxy {
+    /// Create the galaxy and generate its initial active system.
+    pub fn new(map: GalaxyMap,
+               generator: SystemGeneratorFn,
+               active: usize,
+               textures: &mut TextureStore)
+               -> Result<Galaxy, String> {
+        if active >= map.systems.len() {
+            return Err(format!("Galaxy : no system #{} in the galaxy map", active));
+        }
+        let extent = map.systems.iter().fold(0.0, |extent, system| {
+            let distance: f64 = system.position.norm();
+            distance.max(extent)
+        });
+
+        let mut galaxy = Galaxy {
+            map: map,
+            systems: HashMap::new(),
+            active: active,
+            generator: generator,
+            extent: extent,
+        };
+        try!(galaxy.ensure_generated(active, textures));
+        Ok(galaxy)
+    }
+
+    pub fn map(&self) -> &GalaxyMap {
+        &self.map
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn is_generated(&self, system: usize) -> bool {
+        self.systems.contains_key(&system)
+    }
+
+    pub fn active_system(&self) -> &GameSystem {
+        &self.systems[&self.active].0
+    }
+
+    pub fn active_system_mut(&mut self) -> &mut GameSystem {
+        &mut self.systems.get_mut(&self.active).unwrap().0
+    }
+
+    pub fn active_home(&self) -> &ObjectHandle {
+        &self.systems[&self.active].1
+    }
+
+    /// Switch the active system, generating it if needed.
+    pub fn set_active(&mut self,
+                      system: usize,
+                      textures: &mut TextureStore)
+                      -> Result<(), String> {
+        if system >= self.map.systems.len() {
+            return Err(format!("Galaxy : no system #{} in the galaxy map", system));
+        }
+        try!(self.ensure_generated(system, textures));
+        self.active = system;
+        Ok(())
+    }
+
+    /// Scale factor from galaxy units to display units fitting the galaxy
+    /// inside a view of the given dimensions.
+    pub fn view_scale(&self, view_width: f64, view_height: f64) -> f64 {
+        if self.extent <= 0.0 {
+            1.0
+        } else {
+            0.45 * view_width.min(view_height) / self.extent
+        }
+    }
+
+    /// Index of the system drawn under the given position of the galaxy view.
+    pub fn system_at(&self, view_position: Vector2f, scale: f64) -> Option<usize> {
+        self.map.system_at((view_position.0 / scale, view_position.1 / scale),
+                           2.0 * SYSTEM_RADIUS / scale)
+    }
+
+    fn ensure_generated(&mut self,
+                        system: usize,
+                        textures: &mut TextureStore)
+                        -> Result<(), String> {
+        if self.systems.contains_key(&system) {
+            return Ok(());
+        }
+        let node = &self.map.systems[system];
+        let mut rng = StdRng::from_seed(&[node.seed as usize]);
+        let (mut game_system, home) = try!((self.generator)(node, &mut rng, textures));
+        game_system.init(&mut rng);
+        self.systems.insert(system, (game_system, home));
+        Ok(())
+    }
+
+    /// Draw the galaxy map centered on the given context.
+    pub fn render(&self, c: Context, g: &mut G2d, scale: f64, selected: Option<usize>) {
+        let lane = Line::new(HYPERLANE_COLOR, HYPERLANE_RADIUS);
+        for &(a, b) in &self.map.hyperlanes {
+            let (pa, pb) = (self.map.systems[a].position, self.map.systems[b].position);
+            lane.draw([pa.0 * scale, pa.1 * scale, pb.0 * scale, pb.1 * scale],
+                      &c.draw_state,
+                      c.transform,
+                      g);
+        }
+
+        for (i, system) in self.map.systems.iter().enumerate() {
+            let color = if i == self.active {
+                SYSTEM_ACTIVE_COLOR
+            } else if self.is_generated(i) {
+                SYSTEM_VISITED_COLOR
+            } else {
+                SYSTEM_COLOR
+            };
+            let (x, y) = (system.position.0 * scale, system.position.1 * scale);
+            Ellipse::new(color).draw([x - SYSTEM_RADIUS,
+                                      y - SYSTEM_RADIUS,
+                                      2.0 * SYSTEM_RADIUS,
+                                      2.0 * SYSTEM_RADIUS],
+                                     &c.draw_state,
+                                     c.transform,
+                                     g);
+            if selected == Some(i) {
+                let r = 2.0 * SYSTEM_RADIUS;
+                Ellipse::new_border(SYSTEM_SELECTED_COLOR, 1.0)
+                    .draw([x - r, y - r, 2.0 * r, 2.0 * r], &c.draw_state, c.transform, g);
+            }
+        }
+    }
+}
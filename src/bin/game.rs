@@ -1,9 +1,23 @@
+use std::f64::consts::PI;
+use std::path::Path;
+use std::rc::Rc;
+
 use rand::{Rng, StdRng};
 use piston_window::*;
 
-use solar_rustlib::core::{ObjectType, ObjectVisuals, ObjectPropertyValue};
-use solar_rustlib::generator::{TypeGenerator, Distribution};
+use solar_rustlib::util::{Vector, Vector2f};
+
+use solar_rustlib::core::{ObjectType, ObjectVisuals, ObjectPropertyValue, ObjectRegister,
+                          StarGenerator, Composition, CompositionGenerator, Gravity,
+                          CollisionOutcome, ResourceRegistry, ResourceRole, ResourceType};
+use solar_rustlib::core::orbital::{HohmannTransfer, LagrangePoint};
+use solar_rustlib::generator::{TypeGenerator, Distribution, HsvColorGenerator,
+                               PaletteColorGenerator, PlacementConstraints, GalaxyGenerator,
+                               PlanetTextureGenerator, SurfaceType, BeltProfile, BeltGap,
+                               AsteroidFamily, DensityProfile, to_rgb};
 use app::UiCell;
+use galaxy::Galaxy;
+use render::draw_fn_from_texture;
 use ui::ResourceWidget;
 use textures::{TextureStore, TextureHandle};
 
@@ -11,20 +25,57 @@ use objects::*;
 
 const UI_BAR_HEIGHT_FACTOR: f64 = 1.0 / 10.0;
 const UI_MAIN_HEIGHT_FACTOR: f64 = 1.0 - 2.0 * UI_BAR_HEIGHT_FACTOR;
+/// Time during which a notification stays displayed, in seconds.
+const NOTIFICATION_DURATION: f64 = 5.0;
+/// Amount of ore mined at once from an asteroid.
+const MINING_AMOUNT: f64 = 25.0;
+/// Amount of water ice and volatiles harvested at once from a comet.
+const HARVESTING_AMOUNT: f64 = 20.0;
+/// Data file describing the resources of the game.
+const RESOURCES_FILE: &'static str = "assets/resources.txt";
+/// Maximum number of resources displayed in the HUD.
+const MAX_HUD_RESOURCES: usize = 16;
+/// Width of each resource displayed in the HUD, icon included.
+const HUD_RESOURCE_WIDTH: f64 = 200.0;
+
+/// What the main section of the game displays.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameView {
+    /// The active solar system.
+    System,
+    /// The galaxy map, from which the active system can be switched.
+    Galaxy,
+}
 
 /// Main structure for the solar-rust game proper.
 pub struct SolarRust<R: Rng> {
     /// Random number generator used by the game (generation, simulation...).
     rng: R,
-    system: GameSystem,
+    galaxy: Galaxy,
+    view: GameView,
+    /// Last known position of the mouse cursor, in display coordinates.
+    cursor: Vector2f,
+    /// The system under the cursor in the galaxy view, if any.
+    hovered_system: Option<usize>,
+    /// The object to which a route from the home object is being planned.
+    route_target: Option<ObjectHandle>,
+    /// The asteroid selected for mining, along with its belt.
+    selected_asteroid: Option<(ObjectHandle, AsteroidId)>,
+    /// Message describing the last event of the active system, with its
+    /// remaining display time.
+    notification: Option<(String, f64)>,
     display_width: f64,
     display_height: f64,
     gameview_width: f64,
     gameview_height: f64,
     texture_store: TextureStore,
-    texture_icon_minerals: TextureHandle,
-    texture_icon_energy: TextureHandle,
-    object_home: ObjectHandle,
+    /// The resources displayed in the HUD, those having an icon, along with
+    /// their icon.
+    resource_icons: Vec<(ResourceType, TextureHandle)>,
+    /// Id of the resource extracted from the asteroids.
+    mined_resource: String,
+    /// Id of the resource harvested from the comets.
+    harvested_resource: String,
 }
 
 impl<R: Rng> SolarRust<R> {
@@ -36,8 +87,259 @@ impl<R: Rng> SolarRust<R> {
         self.gameview_height = h * UI_MAIN_HEIGHT_FACTOR;
     }
 
+    pub fn on_button_press(&mut self, button: Button) -> Result<(), String> {
+        match (button, self.view) {
+            (Button::Keyboard(Key::G), GameView::System) => self.view = GameView::Galaxy,
+            (Button::Keyboard(Key::G), GameView::Galaxy) => self.view = GameView::System,
+            (Button::Mouse(MouseButton::Left), GameView::Galaxy) => {
+                if let Some(system) = self.hovered_system {
+                    try!(self.galaxy.set_active(system, &mut self.texture_store));
+                    self.view = GameView::System;
+                    self.route_target = None;
+                    self.selected_asteroid = None;
+                }
+            }
+            (Button::Mouse(MouseButton::Left), GameView::System) => {
+                let item = self.item_under_cursor();
+                self.selected_asteroid = match item {
+                    Some(SpatialItem::Part(ref belt, id)) if belt.borrow().as_belt().is_some() => {
+                        Some((belt.clone(), id))
+                    }
+                    _ => None,
+                };
+                self.route_target = item.map(|item| item.object().clone());
+            }
+            (Button::Keyboard(Key::M), GameView::System) => self.mine_selected_asteroid(),
+            (Button::Keyboard(Key::H), GameView::System) => self.harvest_target_comet(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    pub fn on_mouse_move(&mut self, position: [f64; 2]) {
+        self.cursor = (position[0], position[1]);
+        self.hovered_system = match self.view {
+            GameView::Galaxy => {
+                let center = self.gameview_center();
+                let scale = self.galaxy.view_scale(self.gameview_width, self.gameview_height);
+                self.galaxy.system_at((self.cursor.0 - center.0, self.cursor.1 - center.1), scale)
+            }
+            GameView::System => None,
+        };
+    }
+
+    /// The object or part of the active system closest to the cursor, if
+    /// close enough.
+    fn item_under_cursor(&self) -> Option<SpatialItem> {
+        const MAX_DISTANCE: f64 = 10.0;
+
+        let center = self.gameview_center();
+        let position = (self.cursor.0 - center.0, self.cursor.1 - center.1);
+        self.galaxy
+            .active_system()
+            .spatial_index()
+            .nearest(position, MAX_DISTANCE)
+            .map(|entry| entry.value.clone())
+    }
+
+    /// Mine the selected asteroid, bringing the extracted ores to the home
+    /// object's stock of the mined resource.
+    fn mine_selected_asteroid(&mut self) {
+        let (belt_handle, id) = match self.selected_asteroid {
+            Some((ref belt, id)) => (belt.clone(), id),
+            None => return,
+        };
+        let mut belt_object = belt_handle.borrow_mut();
+        let belt = match belt_object.as_belt_mut() {
+            Some(belt) => belt,
+            None => return,
+        };
+        let composition = match belt.asteroid_register(id) {
+            Some(register) => Composition::from_register(register),
+            None => return,
+        };
+        let mut extracted = 0.0;
+        for deposit in &composition.deposits {
+            extracted += belt.mine_asteroid(id, deposit.ore, MINING_AMOUNT * deposit.abundance);
+        }
+        if let Some(stock) = self.galaxy
+                                 .active_home()
+                                 .borrow_mut()
+                                 .register_mut()
+                                 .get_float_mut(self.mined_resource.clone()) {
+            *stock += extracted;
+        }
+        if belt.asteroid_register(id).is_none() {
+            self.selected_asteroid = None;
+            self.notification = Some((format!("Asteroid #{} was mined out", id),
+                                      NOTIFICATION_DURATION));
+        }
+    }
+
+    /// Harvest the targeted comet if it is active, bringing the extracted water
+    /// ice and volatiles to the home object's stock of the harvested resource.
+    fn harvest_target_comet(&mut self) {
+        let target = match self.route_target {
+            Some(ref target) => target.clone(),
+            None => return,
+        };
+        let mut target_object = target.borrow_mut();
+        let comet = match target_object.as_comet_mut() {
+            Some(comet) => comet,
+            None => return,
+        };
+        if !comet.is_active() {
+            self.notification = Some(("The comet is too far from the star to be harvested"
+                                          .to_string(),
+                                      NOTIFICATION_DURATION));
+            return;
+        }
+        let extracted = comet.harvest(HARVESTING_AMOUNT);
+        if let Some(stock) = self.galaxy
+                                 .active_home()
+                                 .borrow_mut()
+                                 .register_mut()
+                                 .get_float_mut(self.harvested_resource.clone()) {
+            *stock += extracted;
+        }
+    }
+
+    /// Describe the targeted comet, if the route target is one.
+    fn target_comet_description(&self) -> Option<String> {
+        let target = match self.route_target {
+            Some(ref target) => target,
+            None => return None,
+        };
+        let label = object_label(target);
+        let target = target.borrow();
+        target.as_comet().map(|comet| {
+            let state = if comet.is_active() {
+                "active - H : harvest it"
+            } else {
+                "inactive until closer to the star"
+            };
+            format!("{} : {:.0} units of volatiles left, {}",
+                    label,
+                    comet.volatiles(),
+                    state)
+        })
+    }
+
+    /// Tell which body the targeted object is orbiting, if it is in free
+    /// flight.
+    fn target_free_flight_description(&self) -> Option<String> {
+        let target = match self.route_target {
+            Some(ref target) => target,
+            None => return None,
+        };
+        let position = match *target.borrow().orbit() {
+            Orbit::Free { position, .. } => position,
+            _ => return None,
+        };
+        self.galaxy
+            .active_system()
+            .dominant_body(position)
+            .map(|body| format!("{} is orbiting {}", object_label(target), object_label(&body)))
+    }
+
+    /// Describe the selected asteroid, if it still exists.
+    fn selected_asteroid_description(&self) -> Option<String> {
+        let (belt, id) = match self.selected_asteroid {
+            Some((ref belt, id)) => (belt.borrow(), id),
+            None => return None,
+        };
+        belt.as_belt()
+            .and_then(|belt| belt.asteroid_register(id))
+            .map(|register| {
+                format!("Asteroid #{} : {:.0} units of ore left - M : mine it",
+                        id,
+                        register.get_float("mass").map_or(0.0, |mass| *mass))
+            })
+    }
+
+    /// Describe the Hohmann transfer from the body the home object belongs to,
+    /// to the one of the route target.
+    fn route_plan(&self) -> Option<String> {
+        let target = match self.route_target {
+            Some(ref target) => target,
+            None => return None,
+        };
+        let (departure, star) = match primary_body(self.galaxy.active_home()) {
+            Some(primary) => primary,
+            None => return None,
+        };
+        let arrival = match primary_body(target) {
+            Some((arrival, ref arrival_star)) if Rc::ptr_eq(&star, arrival_star) => arrival,
+            _ => return Some(format!("No route to {}", object_label(target))),
+        };
+        if Rc::ptr_eq(&departure, &arrival) {
+            return Some(format!("{} is in the home's vicinity", object_label(target)));
+        }
+
+        let star = star.borrow();
+        let star_mass = match star.register().get_float("mass") {
+            Some(mass) => *mass,
+            None => return None,
+        };
+        // the angles decrease along the orbits
+        let polar = |body: &ObjectHandle| {
+            let (dx, dy) = body.borrow().position().minus(&star.position());
+            ((dx * dx + dy * dy).sqrt(), -dy.atan2(dx))
+        };
+        let ((r1, angle1), (r2, angle2)) = (polar(&departure), polar(&arrival));
+        let transfer = match HohmannTransfer::new(star_mass, r1, r2) {
+            Ok(transfer) => transfer,
+            Err(_) => return None,
+        };
+        let window = match transfer.next_window(angle1, angle2) {
+            Some(wait) => format!("next window in {:.1} days", wait),
+            None => "no launch window".to_string(),
+        };
+        Some(format!("Route to {} : delta-v {:.1}, {:.1} days of travel, {}",
+                     object_label(target),
+                     transfer.total_delta_v(),
+                     transfer.duration,
+                     window))
+    }
+
+    /// Center of the main game view, in display coordinates.
+    fn gameview_center(&self) -> Vector2f {
+        (self.display_width / 2.0,
+         self.display_height * UI_BAR_HEIGHT_FACTOR + self.gameview_height / 2.0)
+    }
+
     pub fn update(&mut self, dt: f64) {
-        self.system.update(dt);
+        let system = self.galaxy.active_system_mut();
+        system.update(dt);
+        for event in system.drain_events() {
+            let message = match event {
+                SystemEvent::ObjectRemoved(object) => {
+                    format!("{} was destroyed", object_label(&object))
+                }
+                SystemEvent::Orphaned { object, policy } => {
+                    let fate = match policy {
+                        OrphanPolicy::Freeze => "is adrift",
+                        OrphanPolicy::InheritGrandparent => "was captured by a new orbit",
+                        OrphanPolicy::Escape => "is escaping",
+                    };
+                    format!("{} {}", object_label(&object), fate)
+                }
+                // only the impacts on whole objects are worth notifying
+                SystemEvent::Collision { first: SpatialItem::Object(object), .. } |
+                SystemEvent::Collision { second: SpatialItem::Object(object), .. } => {
+                    format!("{} was hit by an asteroid", object_label(&object))
+                }
+                SystemEvent::Collision { .. } => continue,
+            };
+            self.notification = Some((message, NOTIFICATION_DURATION));
+        }
+
+        if let Some((_, ref mut remaining)) = self.notification {
+            *remaining -= dt;
+        }
+        if self.notification.as_ref().map_or(false, |&(_, remaining)| remaining <= 0.0) {
+            self.notification = None;
+        }
     }
 
     pub fn update_ui(&mut self, ui: &mut UiCell) {
@@ -49,8 +351,8 @@ impl<R: Rng> SolarRust<R> {
             SECTION_MAIN,
             SECTION_BOTTOM,
             TITLE,
-            RESOURCES_MINERALS,
-            RESOURCES_ENERGY,
+            VIEW_HINT,
+            RESOURCES with MAX_HUD_RESOURCES,
         );
 
         // Master canvas :
@@ -84,28 +386,67 @@ impl<R: Rng> SolarRust<R> {
             .pad(0.0)
             .set(MASTER, ui);
 
-        const TITLE_TEXT: &'static str = "SolarRust alpha-dev";
-        Text::new(TITLE_TEXT)
+        let title_text = format!("SolarRust alpha-dev - {}",
+                                 self.galaxy.map().systems[self.galaxy.active()].name);
+        Text::new(&title_text)
             .color(color::WHITE)
             .middle_of(SECTION_TOP)
             .set(TITLE, ui);
 
-        let object_home = self.object_home.borrow();
+        let hint_text = match self.view {
+            GameView::System => {
+                match self.notification {
+                    Some((ref message, _)) => message.clone(),
+                    None => {
+                        self.selected_asteroid_description()
+                            .or_else(|| self.target_comet_description())
+                            .or_else(|| self.target_free_flight_description())
+                            .or_else(|| self.route_plan())
+                            .unwrap_or_else(|| {
+                                "G : galaxy map - click on a body to plan a route".to_string()
+                            })
+                    }
+                }
+            }
+            GameView::Galaxy => {
+                match self.hovered_system {
+                    Some(system) => {
+                        format!("{} - click to travel there",
+                                self.galaxy.map().systems[system].name)
+                    }
+                    None => "G : back to the system".to_string(),
+                }
+            }
+        };
+        Text::new(&hint_text)
+            .color(color::WHITE)
+            .middle_of(SECTION_BOTTOM)
+            .set(VIEW_HINT, ui);
+
+        let object_home = self.galaxy.active_home().borrow();
         let home_object_reg = object_home.register();
-        ResourceWidget::from_logo(self.texture_icon_minerals.clone())
-            .mid_left_of(SECTION_TOP)
-            .frame(0.0)
-            .color(color::TRANSPARENT)
-            .with_amount(*home_object_reg.get_float("minerals")
-                                         .expect("ResourceWidget : no minerals in target object"))
-            .set(RESOURCES_MINERALS, ui);
-        ResourceWidget::from_logo(self.texture_icon_energy.clone())
-            .mid_right_of(SECTION_TOP)
-            .frame(0.0)
-            .color(color::TRANSPARENT)
-            .with_amount(*home_object_reg.get_float("energy")
-                                         .expect("ResourceWidget : no energy in target object"))
-            .set(RESOURCES_ENERGY, ui);
+        for (i, &(ref resource, ref icon)) in self.resource_icons.iter().enumerate() {
+            // the stock of the storable resources, the rate of the others
+            let (key, unit) = if resource.is_storable() {
+                (resource.id.clone(), resource.unit.clone())
+            } else {
+                (resource.rate_key(), format!("{}/s", resource.unit))
+            };
+            let amount = home_object_reg.get_float(key).cloned().unwrap_or(0.0);
+            let widget = ResourceWidget::from_logo(icon.clone());
+            let widget = if i == 0 {
+                widget.mid_left_of(SECTION_TOP)
+            } else {
+                widget.right_from(RESOURCES + (i - 1), 10.0)
+            };
+            widget.w(HUD_RESOURCE_WIDTH)
+                  .frame(0.0)
+                  .color(color::TRANSPARENT)
+                  .with_name(resource.display_name.clone())
+                  .with_amount(amount)
+                  .with_unit(unit)
+                  .set(RESOURCES + i, ui);
+        }
     }
 
     pub fn render(&mut self, c: Context, g: &mut G2d) {
@@ -124,101 +465,429 @@ impl<R: Rng> SolarRust<R> {
         //           c.transform,
         //           g); // debug background
 
-        self.system.render(c, g);
+        match self.view {
+            GameView::System => self.galaxy.active_system_mut().render(c, g),
+            GameView::Galaxy => {
+                let scale = self.galaxy.view_scale(self.gameview_width, self.gameview_height);
+                self.galaxy.render(c, g, scale, self.hovered_system);
+            }
+        }
     }
 }
 
 impl SolarRust<StdRng> {
-    pub fn test_game(textures: TextureStore,
+    pub fn test_game(mut textures: TextureStore,
                      display_size: Size)
                      -> Result<SolarRust<StdRng>, String> {
         assert_eq!(2.0 * UI_BAR_HEIGHT_FACTOR + UI_MAIN_HEIGHT_FACTOR, 1.0);
 
         let mut rng = try!(StdRng::new().map_err(|e| format!("{:?}", e)));
-        let (mut system, home) = try!(test_system(&mut rng));
-        system.init(&mut rng);
+        let resources = try!(ResourceRegistry::from_file(Path::new(RESOURCES_FILE)));
+        let mut resource_icons = Vec::new();
+        for resource in resources.types() {
+            if let Some(ref icon) = resource.icon {
+                let texture = try!(textures.find(icon.clone()).ok_or_else(|| {
+                    format!("SolarRust : no icon \"{}\" for the resource \"{}\"",
+                            icon,
+                            resource.id)
+                }));
+                resource_icons.push((resource.clone(), texture));
+            }
+        }
+        resource_icons.truncate(MAX_HUD_RESOURCES);
+        // the mined and harvested amounts are added to the home object's stock
+        let (mined_resource, harvested_resource) = {
+            let stock_with_role = |role: ResourceRole| {
+                resources.with_role(role).and_then(|resource| {
+                    if resource.is_storable() {
+                        Ok(resource.id.clone())
+                    } else {
+                        Err(format!("SolarRust : the resource \"{}\" of the role \"{}\" is \
+                                     not storable",
+                                    resource.id,
+                                    role.key()))
+                    }
+                })
+            };
+            (try!(stock_with_role(ResourceRole::Mining)),
+             try!(stock_with_role(ResourceRole::Harvesting)))
+        };
 
-        let texture_icon_minerals = textures.get("minerals_icon.png");
-        let texture_icon_energy = textures.get("energy_icon.png");
+        let galaxy_map = try!(GalaxyGenerator::new().generate(&mut rng));
+        let galaxy = try!(Galaxy::new(galaxy_map,
+                                      Box::new(move |node, rng, textures| {
+                                          generate_system(&node.name, &resources, rng, textures)
+                                      }),
+                                      0,
+                                      &mut textures));
 
         let (w, h) = (display_size.width as f64, display_size.height as f64);
         Ok(SolarRust {
             rng: rng,
-            system: system,
+            galaxy: galaxy,
+            view: GameView::System,
+            cursor: (0.0, 0.0),
+            hovered_system: None,
+            route_target: None,
+            selected_asteroid: None,
+            notification: None,
             display_width: w,
             display_height: h,
             gameview_width: w * UI_MAIN_HEIGHT_FACTOR,
             gameview_height: h * UI_MAIN_HEIGHT_FACTOR,
             texture_store: textures,
-            texture_icon_minerals: texture_icon_energy,
-            texture_icon_energy: texture_icon_minerals,
-            object_home: home,
+            resource_icons: resource_icons,
+            mined_resource: mined_resource,
+            harvested_resource: harvested_resource,
         })
     }
 }
 
-fn test_system<R: 'static + Rng>(rng: &mut R) -> Result<(GameSystem, ObjectHandle), String> {
+/// Generate the content of the star system with the given name, returning it
+/// along with its home station.
+fn generate_system<R: 'static + Rng>(name: &str,
+                                     resources: &ResourceRegistry,
+                                     rng: &mut R,
+                                     textures: &mut TextureStore)
+                                     -> Result<(GameSystem, ObjectHandle), String> {
     use blueprints::TransfertStationBlueprint;
 
-    let mut system = GameSystem::new("Test system");
+    let mut system = GameSystem::new(name);
 
-    let sun = DefaultObjectBuilder::with_visuals(ObjectType::Star,
-                                                 ObjectVisuals::circle(75.0, (255, 255, 0)))
+    let star = try!(StarGenerator::default().generate(rng));
+    let (habitable_inner, habitable_outer) = star.habitable_zone();
+    let mut star_register = ObjectRegister::new();
+    star.fill_register(&mut star_register);
+    let sun = DefaultObjectBuilder::with_visuals(ObjectType::Star, star.visuals())
                   .orbit(Orbit::Fixed((0.0, 0.0)))
+                  .register(star_register)
                   .build();
-    let planet1 = DefaultObjectBuilder::with_visuals(ObjectType::Planet,
-                                                     ObjectVisuals::circle(40.0, (40, 15, 180)))
-                      .orbit(Orbit::Circular {
-                          altitude: 125.0,
-                          orbital_speed: 0.1,
-                          angle: 0f64.to_radians(),
-                          origin: sun.clone(),
-                      })
+
+    let mut bodies_colors = HsvColorGenerator::default()
+                                .hue(Distribution::Range {
+                                         low: 180.0,
+                                         high: 320.0,
+                                     }
+                                     .into());
+    let planet1_visuals = ObjectVisuals::circle(40.0, to_rgb(try!(bodies_colors.generate(rng))));
+    let moon1_visuals = ObjectVisuals::circle(10.0, to_rgb(try!(bodies_colors.generate(rng))));
+
+    let star_radius = star.visuals().bounding_radius();
+    // the planet's lane, which spans its moon's orbit, is only occupied once
+    // the moon is placed
+    let planet1_altitude = try!(PlacementConstraints::new()
+                                    .outside_parent(star_radius)
+                                    .sample_altitude(rng,
+                                                     Distribution::Range {
+                                                         low: habitable_inner,
+                                                         high: habitable_outer,
+                                                     }
+                                                     .to_sampler(),
+                                                     planet1_visuals.bounding_radius()));
+    // the masses are exaggerated to compensate for the compressed distances,
+    // while staying below the mass ratio of about 0.0385 past which the
+    // Lagrange points L4 and L5 are unstable
+    let planet1_mass = star.mass *
+                       Distribution::Range {
+                           low: 0.005,
+                           high: 0.035,
+                       }
+                       .to_sampler()(rng);
+    let mut planet1_register = ObjectRegister::new();
+    set_mass(&mut planet1_register, planet1_mass);
+    try!(CompositionGenerator::for_object_type(&ObjectType::Planet).generate(rng))
+        .store(&mut planet1_register);
+    // with some greenhouse effect
+    let planet1_temperature = star.equilibrium_temperature(planet1_altitude) + 33.0;
+    let planet1_texture = try!(body_texture(textures,
+                                            format!("{}/planet1", name),
+                                            SurfaceType::Oceanic,
+                                            planet1_temperature,
+                                            rng));
+    let planet1 = DefaultObjectBuilder::with_visuals(ObjectType::Planet, planet1_visuals.clone())
+                      .draw_fn(draw_fn_from_texture(planet1_texture, 40.0))
+                      .register(planet1_register)
+                      .orbit(try!(Orbit::new_kepler_orbit(planet1_altitude,
+                                                          0f64.to_radians(),
+                                                          &sun)))
+                      .rotation(Rotation::spinning(30.0, 0.0))
                       .build();
-    let moon1 = DefaultObjectBuilder::with_visuals(ObjectType::Moon,
-                                                   ObjectVisuals::circle(10.0, (200, 0, 150)))
-                    .orbit(Orbit::Circular {
-                        altitude: 50.0,
-                        orbital_speed: 0.3,
-                        angle: -90f64.to_radians(),
-                        origin: planet1.clone(),
-                    })
-                    .build();
+
+    // moon kept from crossing into the star, if there is room for it
+    let moon1_altitude = PlacementConstraints::new()
+                             .outside_parent(planet1_visuals.bounding_radius())
+                             .max_altitude(planet1_altitude - star_radius - 5.0)
+                             .within_hill_sphere(planet1_altitude, planet1_mass, star.mass)
+                             .min_spacing(5.0)
+                             .sample_altitude(rng,
+                                              Distribution::Range {
+                                                  low: 25.0,
+                                                  high: 55.0,
+                                              }
+                                              .to_sampler(),
+                                              moon1_visuals.bounding_radius())
+                             .ok();
+    let moon1 = match moon1_altitude {
+        Some(altitude) => {
+            let mut moon1_register = ObjectRegister::new();
+            set_mass(&mut moon1_register, planet1_mass * 0.012);
+            try!(CompositionGenerator::for_object_type(&ObjectType::Moon).generate(rng))
+                .store(&mut moon1_register);
+            let moon1_temperature = star.equilibrium_temperature(planet1_altitude);
+            let moon1_texture = try!(body_texture(textures,
+                                                  format!("{}/moon1", name),
+                                                  if moon1_temperature < 220.0 {
+                                                      SurfaceType::Icy
+                                                  } else {
+                                                      SurfaceType::Rocky
+                                                  },
+                                                  moon1_temperature,
+                                                  rng));
+            Some(DefaultObjectBuilder::with_visuals(ObjectType::Moon, moon1_visuals.clone())
+                     .draw_fn(draw_fn_from_texture(moon1_texture, 10.0))
+                     .register(moon1_register)
+                     .orbit(try!(Orbit::new_kepler_orbit(altitude,
+                                                         -90f64.to_radians(),
+                                                         &planet1))
+                                .on_orphan(OrphanPolicy::InheritGrandparent))
+                     .rotation(Rotation::tidally_locked())
+                     .build())
+        }
+        None => None,
+    };
+    let planet1_lane = moon1_altitude.map_or(planet1_visuals.bounding_radius(), |altitude| {
+        altitude + moon1_visuals.bounding_radius()
+    });
+    let mut star_constraints = PlacementConstraints::new()
+                                   .outside_parent(star_radius)
+                                   .min_spacing(10.0);
+    star_constraints.occupy(planet1_altitude, planet1_lane);
 
     let station1 = try!(TransfertStationBlueprint::new()
-                            .orbit(Orbit::new_relative_orbit(60f64.to_radians(),
-                                                             40.0,
-                                                             planet1.clone()))
+                            .resources(resources.clone())
+                            .orbit(try!(Orbit::new_lagrange_orbit(LagrangePoint::L4, &planet1))
+                                       .on_orphan(OrphanPolicy::Escape))
                             .produce(rng));
     station1.borrow_mut()
             .register_mut()
             .add_constant("name", ObjectPropertyValue::text("Station One"), "");
 
+    // ringed gas giant beyond the main belt, if there is room for it
+    let belt_altitude = planet1_altitude + planet1_lane + 40.0;
+    let giant_visuals = ObjectVisuals::circle(30.0, to_rgb(try!(bodies_colors.generate(rng))));
+    let giant_rings_radius = 34.0;
+    let giant_altitude = star_constraints.sample_altitude(rng,
+                                                          Distribution::Range {
+                                                              low: belt_altitude + 80.0,
+                                                              high: belt_altitude + 200.0,
+                                                          }
+                                                          .to_sampler(),
+                                                          giant_rings_radius)
+                                         .ok();
+    let giant = match giant_altitude {
+        Some(altitude) => {
+            let mut giant_register = ObjectRegister::new();
+            set_mass(&mut giant_register,
+                     star.mass *
+                     Distribution::Range {
+                         low: 0.0008,
+                         high: 0.0012,
+                     }
+                     .to_sampler()(rng));
+            try!(CompositionGenerator::for_object_type(&ObjectType::Planet).generate(rng))
+                .store(&mut giant_register);
+            let texture = try!(body_texture(textures,
+                                            format!("{}/giant", name),
+                                            SurfaceType::Gaseous,
+                                            star.equilibrium_temperature(altitude),
+                                            rng));
+            let giant = DefaultObjectBuilder::with_visuals(ObjectType::Planet, giant_visuals)
+                            .draw_fn(draw_fn_from_texture(texture, 30.0))
+                            .register(giant_register)
+                            .orbit(try!(Orbit::new_kepler_orbit(altitude,
+                                                                90f64.to_radians(),
+                                                                &sun)))
+                            .rotation(Rotation::spinning(10.0, 0.0))
+                            .build();
+            let rings = try!(PlanetaryRingBlueprint::new()
+                                 .parent(giant.clone())
+                                 .inner_radius(20.0)
+                                 .outer_radius(giant_rings_radius)
+                                 .particles_number(300)
+                                 .band(4.0, (210, 190, 150))
+                                 .band(3.0, (180, 160, 120))
+                                 .gap(1.5)
+                                 .band(5.0, (220, 210, 180))
+                                 .produce(rng));
+            Some((giant, rings))
+        }
+        None => None,
+    };
+
+    // outpost on the planet's surface, turning with it
+    let outpost = try!(TransfertStationBlueprint::new()
+                           .resources(resources.clone())
+                           .orbit(Orbit::new_relative_orbit(0.0,
+                                                            planet1_visuals.bounding_radius() +
+                                                            2.0,
+                                                            &planet1)
+                                      .rotating_with_origin())
+                           .color((200, 160, 90))
+                           .produce(rng));
+    outpost.borrow_mut()
+           .register_mut()
+           .add_constant("name", ObjectPropertyValue::text("Surface Outpost"), "");
+
+    let rocks_colors = || {
+        PaletteColorGenerator::default()
+            .entry((120, 110, 100), 3.0)
+            .entry((150, 100, 60), 2.0)
+            .entry((90, 90, 95), 1.0)
+            .value_jitter(Distribution::Normal {
+                              mean: 0.0,
+                              std_dev: 0.08,
+                          }
+                          .into())
+    };
+    // main belt beyond the planet, denser at its middle and carved by the
+    // planet's 3:2 resonance, with a family of asteroids
+    let belt_profile = BeltProfile::new(belt_altitude - 30.0, belt_altitude + 30.0)
+                           .density(DensityProfile::Gaussian {
+                               peak: 0.5,
+                               width: 0.3,
+                           })
+                           .gap(BeltGap::resonance(planet1_altitude, 2, 3, 6.0, 0.9))
+                           .family(AsteroidFamily {
+                               altitude: belt_altitude + 10.0,
+                               angle: rng.gen_range(0.0, 2.0 * PI),
+                               altitude_spread: 3.0,
+                               angle_spread: 0.08,
+                               fraction: 0.2,
+                           });
     let mut asteroid_belt_blueprint = AsteroidBeltBlueprint::<R>::new()
-                                          .asteroids_number(35)
+                                          .asteroids_number(50)
+                                          .constraints(star_constraints.shared_lanes(true))
+                                          .profile(belt_profile)
                                           .generator(AsteroidGenerator::default()
                                                          .radius(Distribution::Normal {
                                                                      mean: 10.0,
                                                                      std_dev: 5.0,
                                                                  }
                                                                  .into())
-                                                         .altitude(Distribution::Normal {
-                                                                       mean: 200.0,
-                                                                       std_dev: 10.0,
-                                                                   }
-                                                                   .into())
-                                                         .orbital_speed(Distribution::Normal {
-                                                                            mean: 0.2,
-                                                                            std_dev: 0.05,
-                                                                        }
-                                                                        .into()));
+                                                         .parent_mass(Some(star.mass))
+                                                         .color(Box::new(rocks_colors())));
     let asteroid_belt = try!(asteroid_belt_blueprint.produce(rng));
 
-    system.add_object(sun);
-    system.add_object(planet1);
-    system.add_object(moon1);
-    system.add_object(station1.clone());
-    system.add_object(asteroid_belt);
+    // trojan asteroids, trailing the planet
+    let mut trojans_blueprint = AsteroidBeltBlueprint::<R>::new()
+                                    .asteroids_number(8)
+                                    .origin_orbit(try!(Orbit::new_lagrange_orbit(LagrangePoint::L5,
+                                                                                 &planet1))
+                                                      .on_orphan(OrphanPolicy::InheritGrandparent))
+                                    .generator(AsteroidGenerator::default()
+                                                   .radius(Distribution::Normal {
+                                                               mean: 6.0,
+                                                               std_dev: 2.0,
+                                                           }
+                                                           .into())
+                                                   .altitude(Distribution::Range {
+                                                                 low: 0.0,
+                                                                 high: 18.0,
+                                                             }
+                                                             .into())
+                                                   .orbital_speed(Distribution::Constant(0.1)
+                                                                      .into())
+                                                   .color(Box::new(rocks_colors())));
+    let trojans = try!(trojans_blueprint.produce(rng));
+
+    // comet diving from beyond the giant towards the star
+    let comet = try!(CometBlueprint::new()
+                         .star(sun.clone())
+                         .perihelion((planet1_altitude / 2.0).max(star_radius + 20.0))
+                         .aphelion(belt_altitude + 300.0)
+                         .produce(rng));
+    comet.borrow_mut()
+         .register_mut()
+         .add_constant("name", ObjectPropertyValue::text(&format!("Comet {}", name)), "");
+
+    // free-flying probe, launched on a circular orbit beyond the belt
+    system.enable_physics(Gravity::game());
+    system.enable_collisions(CollisionOutcome::Bounce { restitution: 0.8 });
+    system.enable_shadows();
+    let probe_altitude = planet1_altitude + planet1_lane + 90.0;
+    let probe_speed = (Gravity::game().gravitational_constant * star.mass / probe_altitude).sqrt();
+    let probe = DefaultObjectBuilder::with_visuals(ObjectType::Satellite,
+                                                   ObjectVisuals::square(4.0, (220, 220, 220)))
+                    .orbit(Orbit::Free {
+                        position: (0.0, -probe_altitude),
+                        velocity: (-probe_speed, 0.0),
+                    })
+                    .build();
+
+    try!(system.add_object(sun));
+    try!(system.add_object(planet1));
+    if let Some(moon1) = moon1 {
+        try!(system.add_object(moon1));
+    }
+    if let Some((giant, rings)) = giant {
+        try!(system.add_object(giant));
+        try!(system.add_object(rings));
+    }
+    try!(system.add_object(station1.clone()));
+    try!(system.add_object(outpost));
+    try!(system.add_object(asteroid_belt));
+    try!(system.add_object(trojans));
+    try!(system.add_object(comet));
+    try!(system.add_object(probe));
 
     Ok((system, station1))
 }
+
+/// Name of the object for the player, i.e. its "name" if it has any.
+fn object_label(object: &ObjectHandle) -> String {
+    let object = object.borrow();
+    match object.register().get_text("name") {
+        Some(name) => name.clone(),
+        None => format!("A {}", format!("{:?}", object.object_type()).to_lowercase()),
+    }
+}
+
+/// The body orbiting the root of the object's orbits hierarchy (i.e. the star)
+/// which the object belongs to, along with that root.
+fn primary_body(object: &ObjectHandle) -> Option<(ObjectHandle, ObjectHandle)> {
+    let mut body = object.clone();
+    let mut parent = match object.borrow().orbit().origin() {
+        Some(parent) => parent,
+        None => return None,
+    };
+    loop {
+        let grandparent = parent.borrow().orbit().origin();
+        match grandparent {
+            Some(grandparent) => {
+                body = parent;
+                parent = grandparent;
+            }
+            None => return Some((body, parent)),
+        }
+    }
+}
+
+fn set_mass(register: &mut ObjectRegister, mass: f64) {
+    register.add_constant("mass", ObjectPropertyValue::Float(mass), "Mass, in solar masses.");
+    register.set_display_name("mass", "Mass");
+}
+
+/// Generate the surface texture of a body with the given mean temperature, and
+/// register it in the store under the given key.
+fn body_texture<R: Rng>(textures: &mut TextureStore,
+                        key: String,
+                        surface_type: SurfaceType,
+                        temperature: f64,
+                        rng: &mut R)
+                        -> Result<TextureHandle, String> {
+    let image = try!(PlanetTextureGenerator::default()
+                         .surface_type(surface_type)
+                         .temperature(Distribution::Constant(temperature).into())
+                         .generate(rng));
+    textures.register(key, &image)
+}
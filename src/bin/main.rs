@@ -1,5 +1,6 @@
 mod app;
 mod blueprints;
+mod galaxy;
 mod game;
 mod objects;
 mod render;
@@ -13,6 +14,7 @@ extern crate graphics;
 #[macro_use] extern crate conrod;
 
 #[macro_use] extern crate solar_rustlib;
+#[macro_use] extern crate solar_rust_derive;
 
 fn main() {
     match app::SolarRustApp::new().run() {
@@ -5,7 +5,10 @@ use std::f64::consts::PI;
 use rand::Rng;
 use piston_window::{Context, G2d, Transformed, Ellipse};
 
-use solar_rustlib::core::{ObjectType, ObjectRegister};
+use solar_rustlib::core::{ObjectType, ObjectRegister, ObjectPropertyValue, Composition,
+                          CompositionGenerator, OreType};
+use solar_rustlib::core::composition::mine;
+use solar_rustlib::core::orbital::{circular_angular_speed, gravitational_parameter};
 use solar_rustlib::generator::*;
 use solar_rustlib::util::*;
 use objects::*;
@@ -13,40 +16,226 @@ use objects::*;
 const ASTEROID_BORDER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
 const ASTEROID_BORDER_RADIUS: f64 = 1.0;
 
+/// Identifier of an asteroid, unique within its belt.
+pub type AsteroidId = usize;
+
 /// An individual asteroid.
 pub struct Asteroid {
+    id: AsteroidId,
+    /// The asteroid's deposits, and its remaining "mass".
+    register: ObjectRegister,
     radius: f64,
     color: [f32; 4],
     orbit_altitude: f64,
     orbit_angle: f64,
     orbit_speed: f64,
+    /// Velocity relative to the belt's origin once knocked out of its orbit
+    /// by a collision, the asteroid then flying freely.
+    velocity: Option<Vector2f>,
     position: Vector2f,
     visuals: (Ellipse, f64),
 }
 
+impl Asteroid {
+    /// Return an asteroid looking like this one, with the given part's size,
+    /// and position and velocity relative to the belt's origin. It flies
+    /// freely from then on, and its deposits are the share of this asteroid's
+    /// corresponding to its size.
+    fn with_motion(&self,
+                   id: AsteroidId,
+                   position: Vec2,
+                   velocity: Vec2,
+                   radius: f64)
+                   -> Asteroid {
+        let share = (radius / self.radius()).powi(2);
+        Asteroid {
+            id: id,
+            register: asteroid_register(&Composition::from_register(&self.register)
+                                             .scaled(share)),
+            radius: radius * 2.0,
+            color: self.color,
+            orbit_altitude: position.length(),
+            orbit_angle: position.angle(),
+            orbit_speed: self.orbit_speed,
+            velocity: Some(velocity.into()),
+            position: position.into(),
+            visuals: (self.visuals.0, -radius),
+        }
+    }
+
+    /// Move the asteroid during 'dt' seconds, along its orbit or in free
+    /// flight. A free asteroid is attracted by the belt's origin if its mass
+    /// is known, and moves in a straight line otherwise.
+    fn update(&mut self, dt: f64, parent_mass: Option<f64>) {
+        match self.velocity {
+            Some(velocity) => {
+                let mut position = Vec2::from(self.position);
+                let mut velocity = Vec2::from(velocity);
+                let altitude = position.length();
+                if let Some(mass) = parent_mass {
+                    if altitude > 0.0 {
+                        velocity -= position * (gravitational_parameter(mass) * dt /
+                                                altitude.powi(3));
+                    }
+                }
+                position += velocity * dt;
+                self.velocity = Some(velocity.into());
+                self.position = position.into();
+                self.orbit_altitude = position.length();
+                self.orbit_angle = position.angle();
+            }
+            None => {
+                let angle = (self.orbit_angle - self.orbit_speed * dt) % (2.0 * PI);
+                self.orbit_angle = angle;
+                self.position = (self.orbit_altitude * angle.cos(),
+                                 self.orbit_altitude * angle.sin());
+            }
+        }
+    }
+
+    /// Velocity relative to the belt's origin.
+    fn velocity(&self) -> Vec2 {
+        match self.velocity {
+            Some(velocity) => velocity.into(),
+            // the angle decreases over time
+            None => -Vec2::from(self.position).perpendicular() * self.orbit_speed,
+        }
+    }
+
+    fn mass(&self) -> f64 {
+        self.register.get_float("mass").map_or(0.0, |mass| *mass)
+    }
+}
+
+/// The register of an asteroid of the given composition, entirely mineable.
+fn asteroid_register(composition: &Composition) -> ObjectRegister {
+    let mut register = ObjectRegister::new();
+    composition.store(&mut register);
+    register.add_property("mass",
+                          ObjectPropertyValue::Float(composition.total_reserve()),
+                          "Remaining mass of the asteroid.");
+    register.set_display_name("mass", "Mass");
+    register
+}
+
+impl Placeable for Asteroid {
+    fn altitude(&self) -> f64 {
+        self.orbit_altitude
+    }
+
+    fn radius(&self) -> f64 {
+        // the radius is drawn as the asteroid's width
+        self.radius / 2.0
+    }
+}
+
 /// 'Object' regrouping several asteroids.
 pub struct AsteroidBeltObject {
+    /// The belt's deposits, those of all its asteroids.
     register: ObjectRegister,
     asteroids: Vec<Asteroid>,
+    next_id: AsteroidId,
+    /// Mass of the body orbited by the belt, if the asteroids follow Kepler's
+    /// third law. It also attracts the asteroids flying freely.
+    parent_mass: Option<f64>,
     origin_position: Vector2f,
     origin_orbit: Orbit,
 }
 
+impl AsteroidBeltObject {
+    pub fn asteroid_ids(&self) -> Vec<AsteroidId> {
+        self.asteroids.iter().map(|asteroid| asteroid.id).collect()
+    }
+
+    fn asteroid_index(&self, id: AsteroidId) -> Option<usize> {
+        self.asteroids.iter().position(|asteroid| asteroid.id == id)
+    }
+
+    /// The register of the asteroid, if it still exists.
+    pub fn asteroid_register(&self, id: AsteroidId) -> Option<&ObjectRegister> {
+        self.asteroid_index(id).map(|i| &self.asteroids[i].register)
+    }
+
+    /// The position of the asteroid in system coordinates, if it still exists.
+    pub fn asteroid_position(&self, id: AsteroidId) -> Option<Vector2f> {
+        self.asteroid_index(id).map(|i| self.origin_position.plus(&self.asteroids[i].position))
+    }
+
+    /// Mine up to 'amount' of the given ore from the asteroid, and return the
+    /// amount actually extracted. The asteroid shrinks with its mass, and is
+    /// removed once nothing is left of it.
+    pub fn mine_asteroid(&mut self, id: AsteroidId, ore: OreType, amount: f64) -> f64 {
+        let i = match self.asteroid_index(id) {
+            Some(i) => i,
+            None => return 0.0,
+        };
+        let extracted = {
+            let asteroid = &mut self.asteroids[i];
+            let mass = asteroid.mass();
+            let extracted = mine(&mut asteroid.register, ore, amount);
+            let remaining = Composition::from_register(&asteroid.register).total_reserve();
+            if let Some(mass) = asteroid.register.get_float_mut("mass") {
+                *mass = remaining;
+            }
+            if mass > 0.0 {
+                asteroid.radius *= (remaining / mass).sqrt();
+                asteroid.visuals.1 = asteroid.radius / -2.0;
+            }
+            extracted
+        };
+        if self.asteroids[i].mass() <= 0.0 {
+            self.asteroids.remove(i);
+        }
+        self.store_composition();
+        extracted
+    }
+
+    /// Remove the asteroid from the belt.
+    /// Return false if the asteroid did not exist.
+    pub fn remove_asteroid(&mut self, id: AsteroidId) -> bool {
+        match self.asteroid_index(id) {
+            Some(i) => {
+                self.asteroids.remove(i);
+                self.store_composition();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Update the belt's deposits from those of its asteroids.
+    fn store_composition(&mut self) {
+        let mut composition = Composition::default();
+        for asteroid in &self.asteroids {
+            composition.merge(&Composition::from_register(&asteroid.register));
+        }
+        // the ores left in no asteroid are missing from the merged composition,
+        // and only the stored deposits are reset since the mutable getter
+        // would create the others
+        for deposit in Composition::from_register(&self.register).deposits {
+            if let Some(reserve) = self.register.get_float_mut(deposit.ore.reserve_key()) {
+                *reserve = 0.0;
+            }
+        }
+        composition.store(&mut self.register);
+    }
+}
+
 impl GameObject for AsteroidBeltObject {
     fn init(&mut self, _: &mut Rng) {
         match self.origin_orbit {
             Orbit::Fixed((x, y)) => self.origin_position = (x, y),
+            Orbit::Free { position, .. } => self.origin_position = position,
             _ => (),
         };
     }
 
     fn update(&mut self, dt: f64) {
-        self.origin_position = self.origin_orbit.compute(dt);
+        if let Some(position) = self.origin_orbit.compute(dt) {
+            self.origin_position = position;
+        }
         for asteroid in &mut self.asteroids {
-            let angle = (asteroid.orbit_angle - asteroid.orbit_speed * dt) % (2.0 * PI);
-            asteroid.orbit_angle = angle;
-            asteroid.position = (asteroid.orbit_altitude * angle.cos(),
-                                 asteroid.orbit_altitude * angle.sin());
+            asteroid.update(dt, self.parent_mass);
         }
     }
 
@@ -74,6 +263,80 @@ impl GameObject for AsteroidBeltObject {
         self.origin_position
     }
 
+    fn bounding_radius(&self) -> f64 {
+        self.asteroids
+            .iter()
+            .map(|asteroid| asteroid.position.norm() + asteroid.radius())
+            .fold(0.0, f64::max)
+    }
+
+    fn parts(&self) -> Vec<ObjectPart> {
+        let origin_velocity = Vec2::from(self.origin_orbit.velocity());
+        self.asteroids
+            .iter()
+            .map(|asteroid| {
+                ObjectPart {
+                    id: asteroid.id,
+                    position: self.origin_position.plus(&asteroid.position),
+                    velocity: (origin_velocity + asteroid.velocity()).into(),
+                    radius: asteroid.radius(),
+                }
+            })
+            .collect()
+    }
+
+    fn replace_parts(&mut self, replacements: Vec<(usize, Vec<ObjectPart>)>) {
+        let origin_position = Vec2::from(self.origin_position);
+        let origin_velocity = Vec2::from(self.origin_orbit.velocity());
+        let mut destroyed = Vec::new();
+        for (id, parts) in replacements {
+            let i = match self.asteroid_index(id) {
+                Some(i) => i,
+                None => continue,
+            };
+            if parts.is_empty() {
+                destroyed.push(id);
+                continue;
+            }
+            // the first part keeps the asteroid's id, the extra fragments get new ones
+            let mut asteroids = Vec::with_capacity(parts.len());
+            for (k, part) in parts.into_iter().enumerate() {
+                let part_id = if k == 0 {
+                    id
+                } else {
+                    self.next_id += 1;
+                    self.next_id - 1
+                };
+                asteroids.push(self.asteroids[i]
+                                   .with_motion(part_id,
+                                                Vec2::from(part.position) - origin_position,
+                                                Vec2::from(part.velocity) - origin_velocity,
+                                                part.radius));
+            }
+            let mut asteroids = asteroids.into_iter();
+            self.asteroids[i] = asteroids.next().unwrap();
+            self.asteroids.extend(asteroids);
+        }
+        self.asteroids.retain(|asteroid| !destroyed.contains(&asteroid.id));
+        self.store_composition();
+    }
+
+    fn as_belt(&self) -> Option<&AsteroidBeltObject> {
+        Some(self)
+    }
+
+    fn as_belt_mut(&mut self) -> Option<&mut AsteroidBeltObject> {
+        Some(self)
+    }
+
+    fn orbit(&self) -> &Orbit {
+        &self.origin_orbit
+    }
+
+    fn orbit_mut(&mut self) -> &mut Orbit {
+        &mut self.origin_orbit
+    }
+
     fn register(&self) -> &ObjectRegister {
         &self.register
     }
@@ -82,113 +345,287 @@ impl GameObject for AsteroidBeltObject {
     }
 }
 
-pub struct AsteroidBeltBlueprint<R: Rng> {
+pub struct AsteroidBeltBlueprint<R: 'static + Rng> {
     generator: AsteroidGenerator<R>,
     asteroids_number: Option<usize>,
     orbit: Option<Orbit>,
+    constraints: Option<PlacementConstraints>,
+    /// Structure of the belt, overriding the generator's altitudes and angles.
+    profile: Option<BeltProfile>,
+    composition: CompositionGenerator<R>,
 }
 
-impl<R: Rng> AsteroidBeltBlueprint<R> {
+impl<R: 'static + Rng> AsteroidBeltBlueprint<R> {
     pub fn new() -> Self {
         AsteroidBeltBlueprint {
             generator: AsteroidGenerator::default(),
             asteroids_number: None,
             orbit: None,
+            constraints: None,
+            profile: None,
+            composition: CompositionGenerator::for_object_type(&ObjectType::Asteroid),
         }
     }
 
     builder_setters!(
         options => {
             asteroids_number { asteroids_number: usize },
-            origin_orbit { orbit: Orbit }
+            origin_orbit { orbit: Orbit },
+            constraints { constraints: PlacementConstraints },
+            profile { profile: BeltProfile }
         };
         others => {
-            generator { generator: AsteroidGenerator<R> }
+            generator { generator: AsteroidGenerator<R> },
+            composition { composition: CompositionGenerator<R> }
         }
     );
 }
 
-impl<R: Rng> GameObjectBlueprint<R> for AsteroidBeltBlueprint<R> {
+impl<R: 'static + Rng> GameObjectBlueprint<R> for AsteroidBeltBlueprint<R> {
     fn produce(&mut self, rng: &mut R) -> Result<ObjectHandle, String> {
         let n = unwrap_or_err!(self.asteroids_number,
                                "AsteroidBeltBlueprint : missing asteroids number parameter");
         let orbit = self.orbit.as_ref().unwrap_or(&Orbit::Fixed((0.0, 0.0))).clone();
+        if self.profile.is_some() {
+            self.generator.profile = self.profile.clone();
+        }
+        let mut asteroids = match self.constraints {
+            Some(ref constraints) => {
+                try!(constraints.clone().generate_many(&mut self.generator, rng, n))
+            }
+            None => try!(self.generator.generate_many(rng, n)),
+        };
+        for (id, asteroid) in asteroids.iter_mut().enumerate() {
+            asteroid.id = id;
+            asteroid.register = asteroid_register(&try!(self.composition.generate(rng)));
+        }
 
-        Ok(Rc::new(RefCell::new(AsteroidBeltObject {
+        let mut belt = AsteroidBeltObject {
             register: ObjectRegister::new(),
-            asteroids: try!(self.generator.generate_many(rng, n)),
+            asteroids: asteroids,
+            next_id: n,
+            parent_mass: self.generator.parent_mass,
             origin_position: match &orbit {
                 &Orbit::Fixed(position) => position,
                 _ => (0.0, 0.0),
             },
             origin_orbit: orbit,
-        })))
+        };
+        belt.store_composition();
+        Ok(Rc::new(RefCell::new(belt)))
     }
 }
 
-pub struct AsteroidGenerator<R: Rng> {
+/// Generates the asteroids composing an 'AsteroidBeltObject'.
+#[derive(TypeGenerator)]
+#[generator(generated = "Asteroid", finish = "assemble")]
+pub struct AsteroidGenerator<R: 'static + Rng> {
+    /// Altitude of the asteroids, if they do not follow a belt profile.
+    #[generator(optional)]
     altitude: Option<SamplerFunction<f64, R>>,
+    #[generator(default = "Distribution::Range { low: 0.0, high: 2.0 * PI }")]
     angle: Option<SamplerFunction<f64, R>>,
     radius: Option<SamplerFunction<f64, R>>,
+    /// Angular speed of the asteroids, if it does not derive from the parent's mass.
+    #[generator(setter = "orbital_speed", optional)]
     speed: Option<SamplerFunction<f64, R>>,
-    color: ColorGenerator<R>,
+    #[generator(default = "Box::new(ColorGenerator::default())")]
+    color: BoxedColorGenerator<R>,
+    /// Mass of the body orbited by the belt : when set, the asteroids' angular
+    /// speed follows Kepler's third law.
+    #[generator(default = "None")]
+    parent_mass: Option<f64>,
+    /// Structure of the belt, from which the asteroids' altitude and angle are
+    /// sampled when set.
+    #[generator(default = "None")]
+    profile: Option<BeltProfile>,
 }
 
-impl<R: Rng> AsteroidGenerator<R> {
-    builder_setters!(
-        options => {
-            altitude { altitude: SamplerFunction<f64, R> },
-            angle { angle: SamplerFunction<f64, R> },
-            radius { radius: SamplerFunction<f64, R> },
-            orbital_speed { speed: SamplerFunction<f64, R> }
+impl<R: 'static + Rng> AsteroidGenerator<R> {
+    fn assemble(&mut self,
+                rng: &mut R,
+                samples: AsteroidGeneratorSamples)
+                -> Result<Asteroid, String> {
+        let color = try!(self.color.generate(rng));
+        let ellipse = Ellipse::new_border(ASTEROID_BORDER_COLOR, ASTEROID_BORDER_RADIUS)
+                          .color(color);
+        let neg_half_radius = samples.radius / -2.0;
+        let (altitude, angle) = match (self.profile.as_ref(), samples.altitude) {
+            (Some(profile), _) => profile.sample(rng),
+            (None, Some(altitude)) => (altitude, samples.angle),
+            (None, None) => {
+                return Err("AsteroidGenerator : unspecified altitude distribution or belt \
+                            profile"
+                               .into())
+            }
         };
-        others => {
-            color { color: ColorGenerator<R> }
-        }
-    );
+        let speed = match (self.parent_mass, samples.speed) {
+            (Some(mass), _) => circular_angular_speed(mass, altitude),
+            (None, Some(speed)) => speed,
+            (None, None) => {
+                return Err("AsteroidGenerator : unspecified speed distribution or parent mass"
+                               .into())
+            }
+        };
+        Ok(Asteroid {
+            id: 0,
+            register: ObjectRegister::new(),
+            radius: samples.radius,
+            color: color,
+            orbit_altitude: altitude,
+            orbit_angle: angle,
+            orbit_speed: speed,
+            velocity: None,
+            position: (0.0, 0.0),
+            visuals: (ellipse, neg_half_radius),
+        })
+    }
 }
 
-impl<R: Rng> TypeGenerator<R> for AsteroidGenerator<R> {
-    type Generated = Asteroid;
-
-    fn default() -> AsteroidGenerator<R> {
-        AsteroidGenerator {
-            altitude: None,
-            angle: Some(Distribution::Range {
-                            low: 0.0,
-                            high: 2.0 * PI,
-                        }
-                        .to_sampler()),
-            radius: None,
-            speed: None,
-            color: ColorGenerator::default(),
-        }
-    }
-
-    fn generate(&mut self, rng: &mut R) -> Result<Asteroid, String> {
-        if self.radius.is_none() {
-            Err("AsteroidGenerator : unspecified radius distribution".into())
-        } else if self.altitude.is_none() {
-            Err("AsteroidGenerator : unspecified altitude distribution".into())
-        } else if self.angle.is_none() {
-            Err("AsteroidGenerator : unspecified angle distribution".into())
-        } else if self.speed.is_none() {
-            Err("AsteroidGenerator : unspecified speed distribution".into())
-        } else {
-            let radius = generator_sample!(self, radius, rng);
-            let color = try!(self.color.generate(rng));
-            let ellipse = Ellipse::new_border(ASTEROID_BORDER_COLOR, ASTEROID_BORDER_RADIUS)
-                              .color(color);
-            let neg_half_radius = radius / -2.0;
-            Ok(Asteroid {
-                radius: radius,
-                color: color,
-                orbit_altitude: generator_sample!(self, altitude, rng),
-                orbit_angle: generator_sample!(self, angle, rng),
-                orbit_speed: generator_sample!(self, speed, rng),
-                position: (0.0, 0.0),
-                visuals: (ellipse, neg_half_radius),
-            })
+#[cfg(test)]
+mod tests {
+    use piston_window::Ellipse;
+
+    use solar_rustlib::core::{Composition, Deposit, OreType};
+    use solar_rustlib::core::orbital::{circular_angular_speed, gravitational_parameter};
+    use super::*;
+
+    /// An asteroid of radius 2 made of 'iron' units of iron, orbiting a body
+    /// of unit mass.
+    fn test_asteroid(id: AsteroidId, altitude: f64, iron: f64) -> Asteroid {
+        let composition = Composition {
+            deposits: vec![Deposit {
+                               ore: OreType::Iron,
+                               abundance: 1.0,
+                               reserve: iron,
+                           }],
+        };
+        Asteroid {
+            id: id,
+            register: asteroid_register(&composition),
+            radius: 4.0,
+            color: [1.0; 4],
+            orbit_altitude: altitude,
+            orbit_angle: 0.0,
+            orbit_speed: circular_angular_speed(1.0, altitude),
+            velocity: None,
+            position: (altitude, 0.0),
+            visuals: (Ellipse::new([1.0; 4]), -2.0),
         }
     }
+
+    fn test_belt(asteroids: Vec<Asteroid>) -> AsteroidBeltObject {
+        let mut belt = AsteroidBeltObject {
+            register: ObjectRegister::new(),
+            next_id: asteroids.len(),
+            asteroids: asteroids,
+            parent_mass: Some(1.0),
+            origin_position: (0.0, 0.0),
+            origin_orbit: Orbit::Fixed((0.0, 0.0)),
+        };
+        belt.store_composition();
+        belt
+    }
+
+    fn test_part(x: f64) -> ObjectPart {
+        ObjectPart {
+            id: 0,
+            position: (x, 0.0),
+            velocity: (0.0, 0.0),
+            radius: 1.0,
+        }
+    }
+
+    fn iron_reserve(register: &ObjectRegister) -> Option<f64> {
+        register.get_float(OreType::Iron.reserve_key()).cloned()
+    }
+
+    #[test]
+    fn test_bounced_asteroid_motion() {
+        let mut belt = test_belt(vec![test_asteroid(0, 100.0, 10.0)]);
+        let orbital_velocity = belt.parts()[0].velocity;
+        assert!(orbital_velocity.0.abs() < 1e-9 && orbital_velocity.1 < 0.0);
+
+        // knocked outwards, the asteroid keeps the velocity of the bounce
+        let bounce = ObjectPart { velocity: (50.0, 0.0), ..test_part(100.0) };
+        belt.replace_parts(vec![(0, vec![bounce.clone()])]);
+        assert_eq!(belt.parts()[0].velocity, (50.0, 0.0));
+
+        // and flies freely, slowed down by the gravity of the belt's origin
+        let dt = 0.1;
+        belt.update(dt);
+        let (x, y) = belt.parts()[0].velocity;
+        assert!((x - (50.0 - gravitational_parameter(1.0) * dt / 100.0f64.powi(2))).abs() < 1e-9);
+        assert_eq!(y, 0.0);
+        assert_eq!(belt.asteroid_position(0), Some((100.0 + x * dt, 0.0)));
+
+        // without the origin's mass, in a straight line
+        belt.parent_mass = None;
+        belt.replace_parts(vec![(0, vec![bounce])]);
+        belt.update(dt);
+        assert_eq!(belt.parts()[0].velocity, (50.0, 0.0));
+        assert_eq!(belt.asteroid_position(0), Some((105.0, 0.0)));
+    }
+
+    #[test]
+    fn test_mine_asteroid() {
+        let mut belt = test_belt(vec![test_asteroid(0, 100.0, 10.0),
+                                      test_asteroid(1, 120.0, 5.0)]);
+        assert_eq!(iron_reserve(&belt.register), Some(15.0));
+
+        // partially mined, the asteroid shrinks with its mass
+        assert_eq!(belt.mine_asteroid(0, OreType::Iron, 4.0), 4.0);
+        assert_eq!(belt.asteroids[0].mass(), 6.0);
+        assert!(belt.asteroids[0].radius() < 2.0);
+        assert_eq!(iron_reserve(&belt.register), Some(11.0));
+
+        // missing ores and asteroids yield nothing
+        assert_eq!(belt.mine_asteroid(0, OreType::Nickel, 4.0), 0.0);
+        assert_eq!(belt.mine_asteroid(7, OreType::Iron, 4.0), 0.0);
+
+        // mined out, the asteroid is removed
+        assert_eq!(belt.mine_asteroid(0, OreType::Iron, 100.0), 6.0);
+        assert_eq!(belt.asteroid_ids(), vec![1]);
+        assert_eq!(iron_reserve(&belt.register), Some(5.0));
+
+        // the belt's reserve drops to zero with its last asteroid, without
+        // creating the deposits it never had
+        assert_eq!(belt.mine_asteroid(1, OreType::Iron, 100.0), 5.0);
+        assert!(belt.asteroid_ids().is_empty());
+        assert_eq!(iron_reserve(&belt.register), Some(0.0));
+        assert!(belt.register.get_float(OreType::Nickel.reserve_key()).is_none());
+    }
+
+    #[test]
+    fn test_remove_asteroid() {
+        let mut belt = test_belt(vec![test_asteroid(0, 100.0, 10.0),
+                                      test_asteroid(1, 120.0, 5.0)]);
+        assert!(belt.remove_asteroid(0));
+        assert!(!belt.remove_asteroid(0));
+        assert_eq!(belt.asteroid_ids(), vec![1]);
+        assert_eq!(iron_reserve(&belt.register), Some(5.0));
+        assert!(belt.asteroid_register(0).is_none());
+    }
+
+    #[test]
+    fn test_replaced_asteroid_ids() {
+        let mut belt = test_belt(vec![test_asteroid(0, 100.0, 10.0),
+                                      test_asteroid(1, 120.0, 5.0),
+                                      test_asteroid(2, 140.0, 5.0)]);
+
+        // a moved asteroid keeps its id, its extra fragments get new ones
+        // and the destroyed ones are removed
+        belt.replace_parts(vec![(0, vec![test_part(105.0)]),
+                                (1, vec![test_part(118.0), test_part(122.0)]),
+                                (2, vec![]),
+                                (9, vec![test_part(50.0)])]);
+        assert_eq!(belt.asteroid_ids(), vec![0, 1, 3]);
+        assert_eq!(belt.asteroid_position(0), Some((105.0, 0.0)));
+        assert_eq!(belt.asteroid_position(1), Some((118.0, 0.0)));
+        assert_eq!(belt.asteroid_position(3), Some((122.0, 0.0)));
+
+        // the ids of the destroyed asteroids are not reused
+        belt.replace_parts(vec![(3, vec![test_part(121.0), test_part(123.0)])]);
+        assert_eq!(belt.asteroid_ids(), vec![0, 1, 3, 4]);
+    }
 }
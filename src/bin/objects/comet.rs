@@ -0,0 +1,263 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::f64::consts::PI;
+
+use rand::Rng;
+use piston_window::{Context, G2d, Ellipse, Line};
+
+use solar_rustlib::core::{ObjectType, ObjectRegister, Composition, CompositionGenerator, OreType};
+use solar_rustlib::core::composition::mine;
+use solar_rustlib::generator::TypeGenerator;
+use solar_rustlib::util::*;
+use render::processed_color;
+use objects::*;
+
+/// Number of segments the tails are drawn with, fading out along the tails.
+const COMET_TAIL_SEGMENTS: usize = 8;
+const COMET_ION_TAIL_COLOR: (u8, u8, u8) = (150, 190, 255);
+const COMET_DUST_TAIL_COLOR: (u8, u8, u8) = (255, 240, 200);
+/// How much the dust tail bends away from the direction of motion.
+const COMET_DUST_TAIL_CURVATURE: f64 = 0.4;
+
+/// 'Object' representing a comet : a small icy body on an eccentric orbit
+/// around its star, which grows tails pointing away from the star when close
+/// enough to it. Its water ice and volatiles can only be harvested then.
+pub struct CometObject {
+    /// The comet's deposits.
+    register: ObjectRegister,
+    orbit: Orbit,
+    position: Vector2f,
+    radius: f64,
+    nucleus: Ellipse,
+    /// Distance to the star below which the comet is active.
+    activity_distance: f64,
+    max_tail_length: f64,
+    /// Current activity, between 0 (inactive) and 1.
+    activity: f64,
+    /// Directions of the ion tail, away from the star, and of the dust tail.
+    ion_tail: Vec2,
+    dust_tail: Vec2,
+}
+
+impl CometObject {
+    pub fn activity(&self) -> f64 {
+        self.activity
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.activity > 0.0
+    }
+
+    /// Remaining amount of water ice and volatiles.
+    pub fn volatiles(&self) -> f64 {
+        let composition = Composition::from_register(&self.register);
+        [OreType::WaterIce, OreType::Volatiles]
+            .iter()
+            .filter_map(|&ore| composition.deposit(ore))
+            .fold(0.0, |sum, deposit| sum + deposit.reserve)
+    }
+
+    /// Harvest up to 'amount' of water ice and volatiles, in proportion of
+    /// their abundances, returning the amount actually extracted. Nothing can
+    /// be harvested while the comet is inactive.
+    pub fn harvest(&mut self, amount: f64) -> f64 {
+        if !self.is_active() {
+            return 0.0;
+        }
+        let composition = Composition::from_register(&self.register);
+        let deposits: Vec<_> = [OreType::WaterIce, OreType::Volatiles]
+                                   .iter()
+                                   .filter_map(|&ore| composition.deposit(ore))
+                                   .collect();
+        let total_abundance = deposits.iter().fold(0.0, |sum, deposit| sum + deposit.abundance);
+        if total_abundance <= 0.0 {
+            return 0.0;
+        }
+        deposits.iter().fold(0.0, |extracted, deposit| {
+            extracted +
+            mine(&mut self.register,
+                 deposit.ore,
+                 amount * deposit.abundance / total_abundance)
+        })
+    }
+
+    fn render_tail(&self, c: Context, g: &mut G2d, direction: Vec2, color: (u8, u8, u8)) {
+        let length = self.max_tail_length * self.activity;
+        let origin = Vec2::from(self.position);
+        for i in 0..COMET_TAIL_SEGMENTS {
+            let (t0, t1) = (i as f64 / COMET_TAIL_SEGMENTS as f64,
+                            (i + 1) as f64 / COMET_TAIL_SEGMENTS as f64);
+            let (start, end) = (origin + direction * (length * t0),
+                                origin + direction * (length * t1));
+            let alpha = (200.0 * (1.0 - t0)) as u8;
+            Line::new(processed_color([color.0, color.1, color.2, alpha]),
+                      self.radius * 0.6 * (1.0 - t0))
+                .draw([start.x, start.y, end.x, end.y], &c.draw_state, c.transform, g);
+        }
+    }
+}
+
+impl GameObject for CometObject {
+    fn init(&mut self, _: &mut Rng) {
+        if let Some(position) = self.orbit.compute(0.0) {
+            self.position = position;
+        }
+    }
+
+    fn update(&mut self, dt: f64) {
+        if let Some(position) = self.orbit.compute(dt) {
+            self.position = position;
+        }
+        let star = match self.orbit.origin() {
+            Some(star) => star,
+            None => {
+                self.activity = 0.0;
+                return;
+            }
+        };
+        let away = Vec2::from(self.position) - Vec2::from(star.borrow().position());
+        let distance = away.length();
+        // the sublimation of the ices grows as the received flux
+        self.activity = if distance > 0.0 {
+            ((self.activity_distance / distance).powi(2) - 1.0).max(0.0).min(1.0)
+        } else {
+            1.0
+        };
+        self.ion_tail = away.normalize();
+        let motion = Vec2::from(self.orbit.velocity()) -
+                     Vec2::from(star.borrow().orbit().velocity());
+        self.dust_tail = (self.ion_tail - motion.normalize() * COMET_DUST_TAIL_CURVATURE)
+                             .normalize();
+    }
+
+    fn render(&self, c: Context, g: &mut G2d) {
+        if self.is_active() {
+            self.render_tail(c, g, self.dust_tail, COMET_DUST_TAIL_COLOR);
+            self.render_tail(c, g, self.ion_tail, COMET_ION_TAIL_COLOR);
+        }
+        self.nucleus.draw([self.position.0 - self.radius,
+                           self.position.1 - self.radius,
+                           2.0 * self.radius,
+                           2.0 * self.radius],
+                          &c.draw_state,
+                          c.transform,
+                          g);
+    }
+
+    fn object_type(&self) -> ObjectType {
+        ObjectType::Comet
+    }
+
+    fn position(&self) -> Vector2f {
+        self.position
+    }
+
+    fn bounding_radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn as_comet(&self) -> Option<&CometObject> {
+        Some(self)
+    }
+
+    fn as_comet_mut(&mut self) -> Option<&mut CometObject> {
+        Some(self)
+    }
+
+    fn orbit(&self) -> &Orbit {
+        &self.orbit
+    }
+
+    fn orbit_mut(&mut self) -> &mut Orbit {
+        &mut self.orbit
+    }
+
+    fn register(&self) -> &ObjectRegister {
+        &self.register
+    }
+
+    fn register_mut(&mut self) -> &mut ObjectRegister {
+        &mut self.register
+    }
+}
+
+/// Builds a comet on an elliptic orbit around the given star, which must have
+/// a "mass".
+/// By default the comet becomes active at twice its perihelion distance.
+pub struct CometBlueprint {
+    star: Option<ObjectHandle>,
+    perihelion: Option<f64>,
+    aphelion: Option<f64>,
+    perihelion_angle: Option<f64>,
+    activity_distance: Option<f64>,
+    radius: f64,
+    max_tail_length: f64,
+    color: (u8, u8, u8),
+}
+
+impl CometBlueprint {
+    pub fn new() -> Self {
+        CometBlueprint {
+            star: None,
+            perihelion: None,
+            aphelion: None,
+            perihelion_angle: None,
+            activity_distance: None,
+            radius: 4.0,
+            max_tail_length: 80.0,
+            color: (200, 210, 220),
+        }
+    }
+
+    builder_setters!(
+        options => {
+            star { star: ObjectHandle },
+            perihelion { perihelion: f64 },
+            aphelion { aphelion: f64 },
+            perihelion_angle { perihelion_angle: f64 },
+            activity_distance { activity_distance: f64 }
+        };
+        others => {
+            radius { radius: f64 },
+            max_tail_length { max_tail_length: f64 },
+            color { color: (u8, u8, u8) }
+        }
+    );
+}
+
+impl<R: Rng> GameObjectBlueprint<R> for CometBlueprint {
+    fn produce(&mut self, rng: &mut R) -> Result<ObjectHandle, String> {
+        let star = unwrap_or_err!(self.star.as_ref(), "CometBlueprint : missing star parameter");
+        let perihelion = unwrap_or_err!(self.perihelion,
+                                        "CometBlueprint : missing perihelion parameter");
+        let aphelion = unwrap_or_err!(self.aphelion,
+                                      "CometBlueprint : missing aphelion parameter");
+        let perihelion_angle = self.perihelion_angle
+                                   .unwrap_or_else(|| rng.gen_range(0.0, 2.0 * PI));
+        let orbit = try!(Orbit::new_elliptic_orbit(perihelion,
+                                                   aphelion,
+                                                   perihelion_angle,
+                                                   rng.gen_range(0.0, 2.0 * PI),
+                                                   star));
+
+        let mut register = ObjectRegister::new();
+        try!(CompositionGenerator::for_object_type(&ObjectType::Comet).generate(rng))
+            .store(&mut register);
+
+        Ok(Rc::new(RefCell::new(CometObject {
+            register: register,
+            orbit: orbit,
+            position: (0.0, 0.0),
+            radius: self.radius,
+            nucleus: Ellipse::new(processed_color([self.color.0,
+                                                   self.color.1,
+                                                   self.color.2,
+                                                   255])),
+            activity_distance: self.activity_distance.unwrap_or(2.0 * perihelion),
+            max_tail_length: self.max_tail_length,
+            activity: 0.0,
+            ion_tail: Vec2::zero(),
+            dust_tail: Vec2::zero(),
+        })))
+    }
+}
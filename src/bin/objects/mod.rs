@@ -1,8 +1,11 @@
 mod belt;
+mod comet;
 mod orbit;
+mod ring;
+mod rotation;
 mod system;
 
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 use std::cell::RefCell;
 
 use rand::Rng;
@@ -12,12 +15,16 @@ use piston_window::{Context, G2d, Transformed};
 use solar_rustlib::core::{ObjectType, ObjectVisuals, ObjectRegister};
 use solar_rustlib::util::Vector2f;
 use render::draw_fn_from_visuals;
-pub use self::orbit::Orbit;
-pub use self::system::GameSystem;
-pub use self::belt::{AsteroidBeltBlueprint, AsteroidGenerator};
+pub use self::orbit::{Orbit, OrphanPolicy};
+pub use self::system::{GameSystem, SystemEvent, SpatialItem};
+pub use self::belt::{AsteroidBeltBlueprint, AsteroidBeltObject, AsteroidGenerator, AsteroidId};
+pub use self::comet::{CometBlueprint, CometObject};
+pub use self::ring::{PlanetaryRingBlueprint, PlanetaryRingObject};
+pub use self::rotation::Rotation;
 
 
 pub type ObjectHandle = Rc<RefCell<GameObject>>;
+pub type WeakObjectHandle = Weak<RefCell<GameObject>>;
 pub type DefaultObjectDrawFn = Box<Fn(Context, &mut G2d)>;
 pub type DefaultObjectUpdateFn = Box<Fn(&mut ObjectRegister, f64)>;
 
@@ -26,14 +33,59 @@ pub trait GameObject {
     fn init(&mut self, r: &mut Rng);
     fn update(&mut self, elapsed: f64);
     fn render(&self, c: Context, g: &mut G2d);
+    /// Draw what lies behind the other objects (e.g. the far side of rings),
+    /// before any object is rendered.
+    fn render_background(&self, _: Context, _: &mut G2d) {}
 
     fn object_type(&self) -> ObjectType;
     fn position(&self) -> Vector2f;
+    /// Radius of the circle centered on the object's position and enclosing it.
+    fn bounding_radius(&self) -> f64;
+    /// The individual parts composing the object, if any (e.g. the asteroids
+    /// of a belt). Such an object is located and collides through its parts
+    /// only.
+    fn parts(&self) -> Vec<ObjectPart> {
+        Vec::new()
+    }
+    /// Replace the parts of the given identifiers (e.g. after a collision)
+    /// with the given parts, none if the part was destroyed. The first part
+    /// replacing another keeps its identifier.
+    fn replace_parts(&mut self, _: Vec<(usize, Vec<ObjectPart>)>) {}
+
+    fn as_belt(&self) -> Option<&AsteroidBeltObject> {
+        None
+    }
+    fn as_belt_mut(&mut self) -> Option<&mut AsteroidBeltObject> {
+        None
+    }
+    fn as_comet(&self) -> Option<&CometObject> {
+        None
+    }
+    fn as_comet_mut(&mut self) -> Option<&mut CometObject> {
+        None
+    }
+
+    fn orbit(&self) -> &Orbit;
+    fn orbit_mut(&mut self) -> &mut Orbit;
+    /// The rotation of the object on itself.
+    fn rotation(&self) -> Rotation {
+        Rotation::Fixed(0.0)
+    }
 
     fn register(&self) -> &ObjectRegister;
     fn register_mut(&mut self) -> &mut ObjectRegister;
 }
 
+/// An individual part of a 'GameObject', in system coordinates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectPart {
+    /// Identifier of the part, unique within its object.
+    pub id: usize,
+    pub position: Vector2f,
+    pub velocity: Vector2f,
+    pub radius: f64,
+}
+
 /// The default GameObject implementation.
 /// It aims to be sufficient for most of the simple objects in the game.
 pub struct DefaultObject {
@@ -43,8 +95,12 @@ pub struct DefaultObject {
     object_type: ObjectType,
     /// The object's current position.
     position: Vector2f,
+    /// The object's bounding radius.
+    radius: f64,
     /// The object's current orbit.
     orbit: Orbit,
+    /// The object's rotation on itself.
+    rotation: Rotation,
     /// The object's property register.
     register: ObjectRegister,
     /// The object's update function.
@@ -57,18 +113,24 @@ impl GameObject for DefaultObject {
     fn init(&mut self, _: &mut Rng) {
         match self.orbit {
             Orbit::Fixed((x, y)) => self.position = (x, y),
+            Orbit::Free { position, .. } => self.position = position,
             _ => (),
         };
     }
 
     fn update(&mut self, dt: f64) {
         self.time_alive += dt;
-        self.position = self.orbit.compute(dt);
+        if let Some(position) = self.orbit.compute(dt) {
+            self.position = position;
+        }
+        let origin_position = self.orbit.origin().map(|origin| origin.borrow().position());
+        self.rotation.update(dt, self.position, origin_position);
         (self.update_fn)(&mut self.register, dt);
     }
 
     fn render(&self, c: Context, g: &mut G2d) {
-        (self.draw_fn)(c.trans(self.position.0, self.position.1), g);
+        (self.draw_fn)(c.trans(self.position.0, self.position.1).rot_rad(self.rotation.angle()),
+                       g);
     }
 
     fn object_type(&self) -> ObjectType {
@@ -79,6 +141,22 @@ impl GameObject for DefaultObject {
         self.position
     }
 
+    fn bounding_radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn orbit(&self) -> &Orbit {
+        &self.orbit
+    }
+
+    fn orbit_mut(&mut self) -> &mut Orbit {
+        &mut self.orbit
+    }
+
+    fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
     fn register(&self) -> &ObjectRegister {
         &self.register
     }
@@ -98,10 +176,12 @@ pub trait GameObjectBlueprint<R: Rng> {
 /// Convenience structure for building any 'DefaultObject' with sensible defaults.
 pub struct DefaultObjectBuilder {
     object_type: ObjectType,
+    radius: f64,
     register: ObjectRegister,
     draw_fn: Option<DefaultObjectDrawFn>,
     update_fn: Option<DefaultObjectUpdateFn>,
     orbit: Option<Orbit>,
+    rotation: Option<Rotation>,
 }
 
 macro_rules! setter_option {
@@ -121,10 +201,12 @@ impl DefaultObjectBuilder {
     pub fn with_visuals(object_type: ObjectType, visuals: ObjectVisuals) -> Self {
         DefaultObjectBuilder {
             object_type: object_type,
+            radius: visuals.bounding_radius(),
             register: ObjectRegister::new(),
             draw_fn: Some(draw_fn_from_visuals(&visuals)),
             update_fn: None,
             orbit: None,
+            rotation: None,
         }
     }
 
@@ -137,7 +219,9 @@ impl DefaultObjectBuilder {
             time_alive: 0f64,
             object_type: self.object_type,
             position: (0.0, 0.0),
+            radius: self.radius,
             orbit: self.orbit.unwrap_or(Orbit::Fixed((0.0, 0.0))),
+            rotation: self.rotation.unwrap_or(Rotation::Fixed(0.0)),
             register: self.register,
             update_fn: self.update_fn
                            .unwrap_or(Box::new(|_: &mut ObjectRegister, _: f64| {})),
@@ -153,4 +237,5 @@ impl DefaultObjectBuilder {
     setter_option!(draw_fn, draw_fn, DefaultObjectDrawFn);
     setter_option!(update_fn, update_fn, DefaultObjectUpdateFn);
     setter_option!(orbit, orbit, Orbit);
+    setter_option!(rotation, rotation, Rotation);
 }
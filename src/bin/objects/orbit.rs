@@ -1,55 +1,424 @@
 use std::f64::consts::PI;
+use std::rc::Rc;
 
-use super::ObjectHandle;
+use solar_rustlib::core::orbital::{circular_angular_speed, angular_speed_from_period,
+                                   EllipticOrbit, LagrangePoint};
+use solar_rustlib::util::{Vector, Vector2f, Vec2};
+use super::{ObjectHandle, WeakObjectHandle};
+
+/// What becomes of an orbiting object when the object it orbits disappears.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrphanPolicy {
+    /// Stay in place ('Orbit::Fixed').
+    Freeze,
+    /// Keep orbiting, around the origin of the disappeared object. Objects
+    /// without grandparent are frozen instead.
+    InheritGrandparent,
+    /// Fly away in free flight with the current velocity ('Orbit::Free').
+    Escape,
+}
 
 #[derive(Clone)]
 pub enum Orbit {
     /// A circular orbit around another 'Object'.
-    /// NB: a 'Weak' reference could be used to handle the case when the reference
-    /// object is dropped.
     Circular {
         altitude: f64,
         orbital_speed: f64,
         /// Current angle of the orbit, in radians.
         angle: f64,
-        origin: ObjectHandle,
+        origin: WeakObjectHandle,
+        on_orphan: OrphanPolicy,
+    },
+    /// An elliptic orbit around another 'Object', following Kepler's laws.
+    /// Useful for comets.
+    Elliptic {
+        /// Orbital elements, mirrored since the angles decrease along the
+        /// orbits.
+        elements: EllipticOrbit,
+        /// Current mean anomaly of the orbit, in radians.
+        mean_anomaly: f64,
+        origin: WeakObjectHandle,
+        on_orphan: OrphanPolicy,
     },
     /// "Relative" orbit : the object will always be at the specified position
     /// relative to the origin object.
     Relative {
         position: (f64, f64),
-        origin: ObjectHandle,
+        origin: WeakObjectHandle,
+        on_orphan: OrphanPolicy,
+        /// If true the position is expressed in the rotating frame of the
+        /// origin, turning with it (e.g. for surface installations).
+        rotating: bool,
+    },
+    /// The object stays at one of the Lagrange points of the origin object and
+    /// the object the origin orbits. Useful for stations and trojan asteroids.
+    Lagrange {
+        point: LagrangePoint,
+        /// Mass of the origin over the total mass of the two bodies.
+        mass_ratio: f64,
+        /// Last position relative to the origin, kept if the origin stops orbiting.
+        offset: Vector2f,
+        origin: WeakObjectHandle,
+        on_orphan: OrphanPolicy,
     },
     /// "Fixed" orbit : the object will never move from its specified initial
     /// position. Useful for stars.
     Fixed((f64, f64)),
+    /// Free flight : the object is moved by the gravity simulation of its
+    /// system (see 'GameSystem::enable_physics') instead of following rails.
+    /// Useful for ships and debris.
+    Free {
+        position: Vector2f,
+        velocity: Vector2f,
+    },
 }
 
 impl Orbit {
     /// Return the relative orbit ('Orbit::Relative') corresponding to the given
     /// relative angle and altitude.
-    pub fn new_relative_orbit(angle: f64, altitude: f64, origin: ObjectHandle) -> Orbit {
+    pub fn new_relative_orbit(angle: f64, altitude: f64, origin: &ObjectHandle) -> Orbit {
         Orbit::Relative {
             position: (altitude * angle.cos(), altitude * angle.sin()),
-            origin: origin,
+            origin: Rc::downgrade(origin),
+            on_orphan: OrphanPolicy::Freeze,
+            rotating: false,
+        }
+    }
+
+    /// Return the circular orbit around the origin object whose angular speed
+    /// follows Kepler's third law, given the "mass" in the origin's register.
+    pub fn new_kepler_orbit(altitude: f64,
+                            angle: f64,
+                            origin: &ObjectHandle)
+                            -> Result<Orbit, String> {
+        let mass = unwrap_or_err!(origin.borrow().register().get_float("mass").cloned(),
+                                  "Orbit : cannot derive a Kepler orbit around an object \
+                                   without mass");
+        Ok(Orbit::Circular {
+            altitude: altitude,
+            orbital_speed: circular_angular_speed(mass, altitude),
+            angle: angle,
+            origin: Rc::downgrade(origin),
+            on_orphan: OrphanPolicy::Freeze,
+        })
+    }
+
+    /// Return the circular orbit around the origin object completing a
+    /// revolution every 'period' game days.
+    #[allow(dead_code)]
+    pub fn new_orbit_with_period(altitude: f64,
+                                 period: f64,
+                                 angle: f64,
+                                 origin: &ObjectHandle)
+                                 -> Orbit {
+        Orbit::Circular {
+            altitude: altitude,
+            orbital_speed: angular_speed_from_period(period),
+            angle: angle,
+            origin: Rc::downgrade(origin),
+            on_orphan: OrphanPolicy::Freeze,
+        }
+    }
+
+    /// Return the elliptic orbit around the origin object with the given closest
+    /// and farthest altitudes, given the "mass" in the origin's register.
+    /// - 'periapsis_angle' is the angle of the closest point, in radians.
+    /// - 'mean_anomaly' is the initial mean anomaly, 0 at the closest point.
+    pub fn new_elliptic_orbit(periapsis: f64,
+                              apoapsis: f64,
+                              periapsis_angle: f64,
+                              mean_anomaly: f64,
+                              origin: &ObjectHandle)
+                              -> Result<Orbit, String> {
+        let mass = unwrap_or_err!(origin.borrow().register().get_float("mass").cloned(),
+                                  "Orbit : cannot derive an elliptic orbit around an object \
+                                   without mass");
+        let elements = try!(EllipticOrbit::from_apsides(mass,
+                                                        periapsis,
+                                                        apoapsis,
+                                                        -periapsis_angle));
+        Ok(Orbit::Elliptic {
+            elements: elements,
+            mean_anomaly: mean_anomaly,
+            origin: Rc::downgrade(origin),
+            on_orphan: OrphanPolicy::Freeze,
+        })
+    }
+
+    /// Return the orbit following the given Lagrange point of the origin object
+    /// and the object it orbits, given the "mass" in their registers.
+    pub fn new_lagrange_orbit(point: LagrangePoint, origin: &ObjectHandle) -> Result<Orbit, String> {
+        let origin_object = origin.borrow();
+        let primary = unwrap_or_err!(origin_object.orbit().origin(),
+                                     "Orbit : the origin of a Lagrange orbit must orbit an object");
+        let primary_mass = unwrap_or_err!(primary.borrow().register().get_float("mass").cloned(),
+                                          "Orbit : cannot derive a Lagrange orbit around an \
+                                           object without mass");
+        let mass = unwrap_or_err!(origin_object.register().get_float("mass").cloned(),
+                                  "Orbit : cannot derive a Lagrange orbit around an object \
+                                   without mass");
+        Ok(Orbit::Lagrange {
+            point: point,
+            mass_ratio: mass / (primary_mass + mass),
+            offset: (0.0, 0.0),
+            origin: Rc::downgrade(origin),
+            on_orphan: OrphanPolicy::Freeze,
+        })
+    }
+
+    /// Set what becomes of the object when its origin disappears.
+    /// Has no effect on the orbits without origin.
+    pub fn on_orphan(mut self, policy: OrphanPolicy) -> Orbit {
+        match self {
+            Orbit::Circular { ref mut on_orphan, .. } |
+            Orbit::Elliptic { ref mut on_orphan, .. } |
+            Orbit::Relative { ref mut on_orphan, .. } |
+            Orbit::Lagrange { ref mut on_orphan, .. } => *on_orphan = policy,
+            _ => (),
+        }
+        self
+    }
+
+    /// Express a relative orbit in the rotating frame of its origin, so that
+    /// the object turns with it. Has no effect on the other orbits.
+    pub fn rotating_with_origin(mut self) -> Orbit {
+        if let Orbit::Relative { ref mut rotating, .. } = self {
+            *rotating = true;
+        }
+        self
+    }
+
+    pub fn orphan_policy(&self) -> Option<OrphanPolicy> {
+        match *self {
+            Orbit::Circular { on_orphan, .. } |
+            Orbit::Elliptic { on_orphan, .. } |
+            Orbit::Relative { on_orphan, .. } |
+            Orbit::Lagrange { on_orphan, .. } => Some(on_orphan),
+            _ => None,
+        }
+    }
+
+    /// The object orbited, if any and if it still exists.
+    pub fn origin(&self) -> Option<ObjectHandle> {
+        match *self {
+            Orbit::Circular { ref origin, .. } |
+            Orbit::Elliptic { ref origin, .. } |
+            Orbit::Relative { ref origin, .. } |
+            Orbit::Lagrange { ref origin, .. } => origin.upgrade(),
+            _ => None,
+        }
+    }
+
+    /// Return true if the orbited object disappeared.
+    pub fn is_orphan(&self) -> bool {
+        match *self {
+            Orbit::Circular { ref origin, .. } |
+            Orbit::Elliptic { ref origin, .. } |
+            Orbit::Relative { ref origin, .. } |
+            Orbit::Lagrange { ref origin, .. } => origin.upgrade().is_none(),
+            _ => false,
+        }
+    }
+
+    /// Current velocity of an object following the orbit, in game units per
+    /// second, including the motion of its origin.
+    pub fn velocity(&self) -> Vector2f {
+        let origin_velocity = match self.origin() {
+            Some(origin) => origin.borrow().orbit().velocity(),
+            None => (0.0, 0.0),
+        };
+        match *self {
+            Orbit::Circular { altitude, orbital_speed, angle, .. } => {
+                // the angle decreases over time
+                (origin_velocity.0 + altitude * orbital_speed * angle.sin(),
+                 origin_velocity.1 - altitude * orbital_speed * angle.cos())
+            }
+            Orbit::Elliptic { ref elements, mean_anomaly, .. } => {
+                let (vx, vy) = elements.velocity(mean_anomaly);
+                (origin_velocity.0 + vx, origin_velocity.1 - vy)
+            }
+            Orbit::Relative { position, rotating: true, .. } => {
+                match self.origin() {
+                    Some(origin) => {
+                        let rotation = origin.borrow().rotation();
+                        let offset = Vec2::from(position).rotate(rotation.angle());
+                        (Vec2::from(origin_velocity) +
+                         offset.perpendicular() * rotation.angular_speed())
+                            .into()
+                    }
+                    None => origin_velocity,
+                }
+            }
+            Orbit::Relative { .. } => origin_velocity,
+            Orbit::Lagrange { offset, .. } => {
+                // the point revolves with the origin around the origin's parent
+                let origin = self.origin();
+                let primary = origin.as_ref().and_then(|origin| origin.borrow().orbit().origin());
+                match (origin, primary) {
+                    (Some(origin), Some(primary)) => {
+                        let primary_velocity = Vec2::from(primary.borrow().orbit().velocity());
+                        let separation = Vec2::from(origin.borrow().position()) -
+                                         Vec2::from(primary.borrow().position());
+                        let relative_velocity = Vec2::from(origin_velocity) - primary_velocity;
+                        let angular_speed = separation.cross(relative_velocity) /
+                                            separation.length_squared();
+                        let position = separation + Vec2::from(offset);
+                        (primary_velocity + position.perpendicular() * angular_speed).into()
+                    }
+                    _ => origin_velocity,
+                }
+            }
+            Orbit::Fixed(_) => (0.0, 0.0),
+            Orbit::Free { velocity, .. } => velocity,
         }
     }
 
-    /// Return the new coordinates of the orbiting object.
+    /// Return the new coordinates of the orbiting object, or None if its origin
+    /// disappeared.
     /// - 'elapsed' is the total elapsed time for the object since its creation,
     ///   in seconds.
-    pub fn compute(&mut self, elapsed: f64) -> (f64, f64) {
+    pub fn compute(&mut self, elapsed: f64) -> Option<(f64, f64)> {
         match *self {
-            Orbit::Circular { altitude, orbital_speed, ref mut angle, ref origin } => {
+            Orbit::Circular { altitude, orbital_speed, ref mut angle, ref origin, .. } => {
                 *angle = (*angle - orbital_speed * elapsed) % (2.0 * PI);
-                let (x, y) = origin.borrow().position();
-                (x + altitude * angle.cos(), y + altitude * angle.sin())
+                origin.upgrade().map(|origin| {
+                    let (x, y) = origin.borrow().position();
+                    (x + altitude * angle.cos(), y + altitude * angle.sin())
+                })
+            }
+            Orbit::Elliptic { ref elements, ref mut mean_anomaly, ref origin, .. } => {
+                *mean_anomaly = (*mean_anomaly + elements.mean_motion() * elapsed) % (2.0 * PI);
+                origin.upgrade().map(|origin| {
+                    let (x, y) = origin.borrow().position();
+                    let (dx, dy) = elements.position(*mean_anomaly);
+                    (x + dx, y - dy)
+                })
             }
-            Orbit::Relative { position, ref origin } => {
-                let (x, y) = origin.borrow().position();
-                (x + position.0, y + position.1)
+            Orbit::Relative { position, ref origin, rotating, .. } => {
+                origin.upgrade().map(|origin| {
+                    let origin = origin.borrow();
+                    let offset = if rotating {
+                        Vec2::from(position).rotate(origin.rotation().angle()).into()
+                    } else {
+                        position
+                    };
+                    origin.position().plus(&offset)
+                })
             }
-            Orbit::Fixed(position) => position,
+            Orbit::Lagrange { point, mass_ratio, ref mut offset, ref origin, .. } => {
+                origin.upgrade().map(|origin| {
+                    let origin = origin.borrow();
+                    let position = origin.position();
+                    if let Some(primary) = origin.orbit().origin() {
+                        // frame of the two bodies, oriented along the origin's motion
+                        let primary = primary.borrow();
+                        let separation = Vec2::from(position) - Vec2::from(primary.position());
+                        let relative_velocity = Vec2::from(origin.orbit().velocity()) -
+                                                Vec2::from(primary.orbit().velocity());
+                        let direction = if separation.cross(relative_velocity) < 0.0 {
+                            -1.0
+                        } else {
+                            1.0
+                        };
+                        let (x, y) = point.coordinates(mass_ratio);
+                        *offset = (separation * (x - 1.0) +
+                                   separation.perpendicular() * (direction * y))
+                                      .into();
+                    }
+                    position.plus(offset)
+                })
+            }
+            Orbit::Fixed(position) => Some(position),
+            Orbit::Free { position, .. } => Some(position),
         }
     }
+
+    /// The orbit replacing this one once its origin, currently at
+    /// 'origin_position' and orbiting 'origin_orbit', disappears.
+    /// - 'position' is the current position of the orbiting object.
+    ///
+    /// Return the new orbit along with the policy actually applied.
+    pub fn orphaned(&self,
+                    position: Vector2f,
+                    origin_orbit: &Orbit)
+                    -> (Orbit, OrphanPolicy) {
+        let policy = match self.orphan_policy() {
+            Some(policy) => policy,
+            None => return (self.clone(), OrphanPolicy::Freeze),
+        };
+        match policy {
+            OrphanPolicy::Freeze => (Orbit::Fixed(position), OrphanPolicy::Freeze),
+            OrphanPolicy::Escape => {
+                (Orbit::Free {
+                    position: position,
+                    velocity: self.velocity(),
+                },
+                 OrphanPolicy::Escape)
+            }
+            OrphanPolicy::InheritGrandparent => {
+                let grandparent = match origin_orbit.origin() {
+                    Some(grandparent) => grandparent,
+                    None => return (Orbit::Fixed(position), OrphanPolicy::Freeze),
+                };
+                let grandparent_position = grandparent.borrow().position();
+                let (dx, dy) = (position.0 - grandparent_position.0,
+                                position.1 - grandparent_position.1);
+                let orbit = match *self {
+                    Orbit::Circular { .. } | Orbit::Elliptic { .. } | Orbit::Lagrange { .. } => {
+                        let altitude = (dx * dx + dy * dy).sqrt();
+                        let angle = dy.atan2(dx);
+                        Orbit::new_kepler_orbit(altitude, angle, &grandparent)
+                            .unwrap_or_else(|_| {
+                                // keep revolving with the disappeared origin
+                                let orbital_speed = match *origin_orbit {
+                                    Orbit::Circular { orbital_speed, .. } => orbital_speed,
+                                    _ => 0.0,
+                                };
+                                Orbit::Circular {
+                                    altitude: altitude,
+                                    orbital_speed: orbital_speed,
+                                    angle: angle,
+                                    origin: Rc::downgrade(&grandparent),
+                                    on_orphan: policy,
+                                }
+                            })
+                            .on_orphan(policy)
+                    }
+                    _ => {
+                        Orbit::Relative {
+                            position: (dx, dy),
+                            origin: Rc::downgrade(&grandparent),
+                            on_orphan: policy,
+                            rotating: false,
+                        }
+                    }
+                };
+                (orbit, OrphanPolicy::InheritGrandparent)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solar_rustlib::core::{ObjectType, ObjectVisuals};
+    use solar_rustlib::core::orbital::GAME_DAY_DURATION;
+    use objects::DefaultObjectBuilder;
+    use super::*;
+
+    #[test]
+    fn test_orbit_with_period() {
+        let origin = DefaultObjectBuilder::with_visuals(ObjectType::Planet,
+                                                        ObjectVisuals::circle(10.0,
+                                                                              (255, 255, 255)))
+                         .orbit(Orbit::Fixed((0.0, 0.0)))
+                         .build();
+        let mut orbit = Orbit::new_orbit_with_period(100.0, 4.0, 0.0, &origin);
+
+        // a quarter of revolution by game day, the angle decreasing over time
+        let (x, y) = orbit.compute(GAME_DAY_DURATION).unwrap();
+        assert!(x.abs() < 1e-9 && (y + 100.0).abs() < 1e-9);
+        let (x, y) = orbit.compute(3.0 * GAME_DAY_DURATION).unwrap();
+        assert!((x - 100.0).abs() < 1e-9 && y.abs() < 1e-9);
+    }
 }
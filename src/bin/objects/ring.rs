@@ -0,0 +1,253 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::f64::consts::PI;
+
+use rand::Rng;
+use piston_window::{Context, G2d, Ellipse};
+
+use solar_rustlib::core::{ObjectType, ObjectRegister};
+use solar_rustlib::core::orbital::circular_angular_speed;
+use solar_rustlib::util::*;
+use render::processed_color;
+use objects::*;
+
+/// Default ratio between the apparent minor and major axes of the rings, seen
+/// at an angle.
+const DEFAULT_RING_TILT: f64 = 0.4;
+/// Default angular speed of the rings of a body without mass, in radians per
+/// second.
+const DEFAULT_RING_ROTATION_SPEED: f64 = 0.5;
+
+struct RingParticle {
+    altitude: f64,
+    angle: f64,
+    size: f64,
+}
+
+/// Particles of the same colour rotating together.
+struct RingBand {
+    ellipse: Ellipse,
+    /// Current rotation of the band, in radians.
+    rotation: f64,
+    /// Angular speed of the band, in radians per second.
+    speed: f64,
+    particles: Vec<RingParticle>,
+}
+
+/// 'Object' representing the ring system of its parent body, drawn around it.
+/// The half of the rings behind the parent is drawn in the background pass.
+pub struct PlanetaryRingObject {
+    register: ObjectRegister,
+    orbit: Orbit,
+    position: Vector2f,
+    outer_radius: f64,
+    tilt: f64,
+    bands: Vec<RingBand>,
+}
+
+impl PlanetaryRingObject {
+    /// Draw the particles in front of the parent, or those behind it.
+    fn render_half(&self, c: Context, g: &mut G2d, front: bool) {
+        for band in &self.bands {
+            for particle in &band.particles {
+                let angle = particle.angle + band.rotation;
+                let (x, y) = (particle.altitude * angle.cos(),
+                              self.tilt * particle.altitude * angle.sin());
+                // the rings seen face-on are entirely behind their parent
+                if (self.tilt < 1.0 && y > 0.0) != front {
+                    continue;
+                }
+                band.ellipse.draw([self.position.0 + x - particle.size / 2.0,
+                                   self.position.1 + y - particle.size / 2.0,
+                                   particle.size,
+                                   particle.size],
+                                  &c.draw_state,
+                                  c.transform,
+                                  g);
+            }
+        }
+    }
+}
+
+impl GameObject for PlanetaryRingObject {
+    fn init(&mut self, _: &mut Rng) {
+        match self.orbit {
+            Orbit::Fixed((x, y)) => self.position = (x, y),
+            Orbit::Free { position, .. } => self.position = position,
+            _ => (),
+        };
+    }
+
+    fn update(&mut self, dt: f64) {
+        if let Some(position) = self.orbit.compute(dt) {
+            self.position = position;
+        }
+        for band in &mut self.bands {
+            band.rotation = (band.rotation - band.speed * dt) % (2.0 * PI);
+        }
+    }
+
+    fn render_background(&self, c: Context, g: &mut G2d) {
+        self.render_half(c, g, false);
+    }
+
+    fn render(&self, c: Context, g: &mut G2d) {
+        self.render_half(c, g, true);
+    }
+
+    fn object_type(&self) -> ObjectType {
+        ObjectType::Ring
+    }
+
+    fn position(&self) -> Vector2f {
+        self.position
+    }
+
+    fn bounding_radius(&self) -> f64 {
+        self.outer_radius
+    }
+
+    fn orbit(&self) -> &Orbit {
+        &self.orbit
+    }
+
+    fn orbit_mut(&mut self) -> &mut Orbit {
+        &mut self.orbit
+    }
+
+    fn register(&self) -> &ObjectRegister {
+        &self.register
+    }
+
+    fn register_mut(&mut self) -> &mut ObjectRegister {
+        &mut self.register
+    }
+}
+
+/// Builds the rings of a body from concentric bands, from the inside out.
+/// The particles are spread between the coloured bands according to their
+/// area, and each band rotates at the Keplerian speed of its middle if the
+/// parent has a "mass".
+pub struct PlanetaryRingBlueprint {
+    parent: Option<ObjectHandle>,
+    inner_radius: Option<f64>,
+    outer_radius: Option<f64>,
+    particles_number: Option<usize>,
+    tilt: f64,
+    rotation_speed: f64,
+    /// Relative width and colour of each band, gaps having no colour.
+    bands: Vec<(f64, Option<(u8, u8, u8)>)>,
+}
+
+impl PlanetaryRingBlueprint {
+    pub fn new() -> Self {
+        PlanetaryRingBlueprint {
+            parent: None,
+            inner_radius: None,
+            outer_radius: None,
+            particles_number: None,
+            tilt: DEFAULT_RING_TILT,
+            rotation_speed: DEFAULT_RING_ROTATION_SPEED,
+            bands: Vec::new(),
+        }
+    }
+
+    builder_setters!(
+        options => {
+            parent { parent: ObjectHandle },
+            inner_radius { inner_radius: f64 },
+            outer_radius { outer_radius: f64 },
+            particles_number { particles_number: usize }
+        };
+        others => {
+            tilt { tilt: f64 },
+            rotation_speed { rotation_speed: f64 }
+        }
+    );
+
+    pub fn band(mut self, width: f64, color: (u8, u8, u8)) -> Self {
+        self.bands.push((width, Some(color)));
+        self
+    }
+
+    pub fn gap(mut self, width: f64) -> Self {
+        self.bands.push((width, None));
+        self
+    }
+}
+
+impl<R: Rng> GameObjectBlueprint<R> for PlanetaryRingBlueprint {
+    fn produce(&mut self, rng: &mut R) -> Result<ObjectHandle, String> {
+        let parent = unwrap_or_err!(self.parent.as_ref(),
+                                    "PlanetaryRingBlueprint : missing parent parameter");
+        let inner_radius = unwrap_or_err!(self.inner_radius,
+                                          "PlanetaryRingBlueprint : missing inner radius \
+                                           parameter");
+        let outer_radius = unwrap_or_err!(self.outer_radius,
+                                          "PlanetaryRingBlueprint : missing outer radius \
+                                           parameter");
+        let n = unwrap_or_err!(self.particles_number,
+                               "PlanetaryRingBlueprint : missing particles number parameter");
+        if inner_radius <= 0.0 || outer_radius <= inner_radius {
+            return Err(format!("PlanetaryRingBlueprint : invalid radii {} and {}",
+                               inner_radius,
+                               outer_radius));
+        }
+
+        // radii of each band
+        let total_width = self.bands.iter().fold(0.0, |sum, &(width, _)| sum + width);
+        let mut bands_radii = Vec::new();
+        let mut radius = inner_radius;
+        for &(width, color) in &self.bands {
+            let next_radius = radius + (outer_radius - inner_radius) * width / total_width;
+            if let Some(color) = color {
+                bands_radii.push((radius, next_radius, color));
+            }
+            radius = next_radius;
+        }
+        let area = |r1: f64, r2: f64| r2 * r2 - r1 * r1;
+        let coloured_area = bands_radii.iter().fold(0.0, |sum, &(r1, r2, _)| sum + area(r1, r2));
+        if !(coloured_area > 0.0) {
+            return Err("PlanetaryRingBlueprint : no coloured band".into());
+        }
+
+        let parent_mass = parent.borrow().register().get_float("mass").cloned();
+        let bands = bands_radii.into_iter()
+                               .map(|(r1, r2, color)| {
+                                   let count = (n as f64 * area(r1, r2) / coloured_area).round();
+                                   let particles = (0..count as usize)
+                                                       .map(|_| {
+                                                           RingParticle {
+                                                               // uniform over the band's area
+                                                               altitude: rng.gen_range(r1 * r1,
+                                                                                       r2 * r2)
+                                                                            .sqrt(),
+                                                               angle: rng.gen_range(0.0, 2.0 * PI),
+                                                               size: rng.gen_range(1.0, 2.5),
+                                                           }
+                                                       })
+                                                       .collect();
+                                   RingBand {
+                                       ellipse: Ellipse::new(processed_color([color.0,
+                                                                              color.1,
+                                                                              color.2,
+                                                                              255])),
+                                       rotation: 0.0,
+                                       speed: parent_mass.map_or(self.rotation_speed, |mass| {
+                                           circular_angular_speed(mass, (r1 + r2) / 2.0)
+                                       }),
+                                       particles: particles,
+                                   }
+                               })
+                               .collect();
+
+        Ok(Rc::new(RefCell::new(PlanetaryRingObject {
+            register: ObjectRegister::new(),
+            orbit: Orbit::new_relative_orbit(0.0, 0.0, parent),
+            position: parent.borrow().position(),
+            outer_radius: outer_radius,
+            tilt: self.tilt,
+            bands: bands,
+        })))
+    }
+}
@@ -0,0 +1,84 @@
+use std::f64::consts::PI;
+
+use solar_rustlib::core::orbital::angular_speed_from_period;
+use solar_rustlib::util::Vector2f;
+
+/// The rotation of an object on itself. Like the orbits, the rotations are
+/// clockwise : their angle decreases over time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rotation {
+    /// The object does not rotate, keeping the given angle in radians.
+    Fixed(f64),
+    /// The object completes a revolution every 'period' game days.
+    Spinning {
+        period: f64,
+        /// Current angle, in radians.
+        angle: f64,
+    },
+    /// The object always shows the same face to the origin of its orbit.
+    TidallyLocked {
+        /// Current angle, in radians, or NaN before the first update.
+        angle: f64,
+        /// Angular speed, in radians per second, as of the last update.
+        angular_speed: f64,
+    },
+}
+
+impl Rotation {
+    pub fn spinning(period: f64, angle: f64) -> Rotation {
+        Rotation::Spinning {
+            period: period,
+            angle: angle,
+        }
+    }
+
+    pub fn tidally_locked() -> Rotation {
+        Rotation::TidallyLocked {
+            angle: ::std::f64::NAN,
+            angular_speed: 0.0,
+        }
+    }
+
+    /// Current angle, in radians.
+    pub fn angle(&self) -> f64 {
+        match *self {
+            Rotation::Fixed(angle) |
+            Rotation::Spinning { angle, .. } => angle,
+            Rotation::TidallyLocked { angle, .. } => if angle.is_nan() { 0.0 } else { angle },
+        }
+    }
+
+    /// Current angular speed, in radians per second.
+    pub fn angular_speed(&self) -> f64 {
+        match *self {
+            Rotation::Fixed(_) => 0.0,
+            Rotation::Spinning { period, .. } => -angular_speed_from_period(period),
+            Rotation::TidallyLocked { angular_speed, .. } => angular_speed,
+        }
+    }
+
+    /// Advance the rotation of an object now at 'position', whose orbit's
+    /// origin is at 'origin_position' if it has any.
+    /// - 'elapsed' is the time elapsed since the last update, in seconds.
+    pub fn update(&mut self, elapsed: f64, position: Vector2f, origin_position: Option<Vector2f>) {
+        match *self {
+            Rotation::Fixed(_) => (),
+            Rotation::Spinning { period, ref mut angle } => {
+                *angle = (*angle - angular_speed_from_period(period) * elapsed) % (2.0 * PI);
+            }
+            Rotation::TidallyLocked { ref mut angle, ref mut angular_speed } => {
+                let (x, y) = match origin_position {
+                    Some(origin) => origin,
+                    None => return,
+                };
+                let facing = (y - position.1).atan2(x - position.0);
+                if !angle.is_nan() && elapsed > 0.0 {
+                    // the change of angle, between -π and π
+                    let change = ((facing - *angle) % (2.0 * PI) + 3.0 * PI) % (2.0 * PI) - PI;
+                    *angular_speed = change / elapsed;
+                }
+                *angle = facing;
+            }
+        }
+    }
+}
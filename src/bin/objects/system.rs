@@ -1,8 +1,62 @@
+use std::f64;
+use std::mem;
+use std::rc::Rc;
+
 use rand::Rng;
-use piston_window::{Context, G2d};
+use piston_window::{Context, G2d, Line};
+
+use solar_rustlib::core::{ObjectType, ObjectRegister, Gravity, PointMass, CollisionBody,
+                          CollisionOutcome, Disk, detect_collisions, illumination};
+use solar_rustlib::core::orbital::sphere_of_influence;
+use solar_rustlib::core::shadow::umbra_length;
+use solar_rustlib::core::star::solar_flux;
+use solar_rustlib::util::{parent_first_order, SpatialIndex, Vector, Vector2f, Vec2};
+use render::processed_color;
+use super::{ObjectHandle, ObjectPart, Orbit, OrphanPolicy};
+
+/// Notable events happening inside a 'GameSystem', to be handled game-side.
+pub enum SystemEvent {
+    /// The object was removed from the system.
+    ObjectRemoved(ObjectHandle),
+    /// The origin of the object's orbit disappeared, and the object's orbit
+    /// was changed according to the given policy.
+    Orphaned {
+        object: ObjectHandle,
+        policy: OrphanPolicy,
+    },
+    /// Two objects or parts collided, closing on each other at the given
+    /// speed (in game units per second).
+    Collision {
+        first: SpatialItem,
+        second: SpatialItem,
+        impact_velocity: f64,
+    },
+}
+
+/// Size of the cells of the systems' spatial index, in game units.
+const SPATIAL_INDEX_CELL_SIZE: f64 = 32.0;
+/// Color of the shadows cast by the bodies.
+const SHADOW_COLOR: [u8; 4] = [0, 0, 0, 120];
+/// Length at which the shadows are cut, in game units.
+const MAX_SHADOW_LENGTH: f64 = 150.0;
+/// Number of segments the shadows are drawn with, narrowing down.
+const SHADOW_SEGMENTS: usize = 6;
+
+/// What an entry of the spatial index of a 'GameSystem' refers to.
+#[derive(Clone)]
+pub enum SpatialItem {
+    Object(ObjectHandle),
+    /// The part of an object with the given identifier (see 'GameObject::parts').
+    Part(ObjectHandle, usize),
+}
 
-use solar_rustlib::core::ObjectRegister;
-use super::ObjectHandle;
+impl SpatialItem {
+    pub fn object(&self) -> &ObjectHandle {
+        match *self {
+            SpatialItem::Object(ref object) | SpatialItem::Part(ref object, _) => object,
+        }
+    }
+}
 
 /// The game structure representing a gravitationally bound solar system, owning
 /// the multiple 'GameObject' instances composing it inside the game simulation.
@@ -11,6 +65,19 @@ pub struct GameSystem {
     /// not concerning one entity in particular.
     register: ObjectRegister,
     objects: Vec<ObjectHandle>,
+    /// Indices of the objects in the order they are to be updated : each
+    /// object's orbit origin comes before it.
+    update_order: Vec<usize>,
+    /// Gravity simulation moving the objects in free flight, if enabled.
+    gravity: Option<Gravity>,
+    /// Outcome of the collisions involving parts, if they are enabled.
+    collisions: Option<CollisionOutcome>,
+    /// Events which happened since the last call to 'drain_events'.
+    events: Vec<SystemEvent>,
+    /// Location of the objects and of their parts, as of the last update.
+    spatial_index: SpatialIndex<SpatialItem>,
+    /// If true the shadows cast by the bodies are drawn.
+    shadows: bool,
 }
 
 impl GameSystem {
@@ -29,29 +96,565 @@ impl GameSystem {
         GameSystem {
             register: register,
             objects: Vec::new(),
+            update_order: Vec::new(),
+            gravity: None,
+            collisions: None,
+            events: Vec::new(),
+            spatial_index: SpatialIndex::new(SPATIAL_INDEX_CELL_SIZE),
+            shadows: false,
         }
     }
 
+    /// Enable the physics mode : the objects with an 'Orbit::Free' are moved by
+    /// the gravity of all the objects having a "mass" in their register.
+    /// The on-rails objects keep following their orbit.
+    /// Without it, the objects in free flight move in a straight line.
+    pub fn enable_physics(&mut self, gravity: Gravity) {
+        self.gravity = Some(gravity);
+    }
+
+    /// Enable the collisions : the objects' parts (see 'GameObject::parts')
+    /// collide with each other and with the objects, which are not affected.
+    pub fn enable_collisions(&mut self, outcome: CollisionOutcome) {
+        self.collisions = Some(outcome);
+    }
+
+    /// Draw the shadows cast by the planets and the moons.
+    pub fn enable_shadows(&mut self) {
+        self.shadows = true;
+    }
+
     /// Add the given 'GameObject' to the system, after having initialized it.
-    pub fn add_object(&mut self, object: ObjectHandle) {
+    /// Fail if the object is already part of the system, or if it orbits one
+    /// of its own descendants.
+    pub fn add_object(&mut self, object: ObjectHandle) -> Result<(), String> {
+        if self.objects.iter().any(|o| Rc::ptr_eq(o, &object)) {
+            return Err("GameSystem : the object is already part of the system".into());
+        }
+        let mut ancestors: Vec<ObjectHandle> = Vec::new();
+        let mut current = object.borrow().orbit().origin();
+        while let Some(origin) = current {
+            if Rc::ptr_eq(&origin, &object) || ancestors.iter().any(|a| Rc::ptr_eq(a, &origin)) {
+                return Err("GameSystem : cannot add an object orbiting one of its own \
+                            descendants"
+                               .into());
+            }
+            current = origin.borrow().orbit().origin();
+            ancestors.push(origin);
+        }
+
         self.objects.push(object);
+        match self.compute_update_order() {
+            Ok(order) => {
+                self.update_order = order;
+                Ok(())
+            }
+            Err(why) => {
+                self.objects.pop();
+                Err(format!("GameSystem : cannot add the object, {}", why))
+            }
+        }
+    }
+
+    /// Order the objects' updates so that each object is updated after the
+    /// origin of its orbit, and thus never reads a stale position.
+    fn compute_update_order(&self) -> Result<Vec<usize>, String> {
+        let index_of = |origin: ObjectHandle| {
+            self.objects.iter().position(|o| Rc::ptr_eq(o, &origin))
+        };
+        let parents: Vec<Option<usize>> = self.objects
+                                              .iter()
+                                              .map(|o| o.borrow().orbit().origin().and_then(&index_of))
+                                              .collect();
+        parent_first_order(&parents)
+    }
+
+    pub fn objects(&self) -> &[ObjectHandle] {
+        &self.objects
+    }
+
+    /// Index of the objects and of their parts for the proximity queries,
+    /// refreshed after each update.
+    pub fn spatial_index(&self) -> &SpatialIndex<SpatialItem> {
+        &self.spatial_index
+    }
+
+    /// The object orbited by the given one, if it is part of the system.
+    pub fn parent(&self, object: &ObjectHandle) -> Option<ObjectHandle> {
+        object.borrow()
+              .orbit()
+              .origin()
+              .and_then(|origin| self.objects.iter().find(|o| Rc::ptr_eq(o, &origin)).cloned())
+    }
+
+    /// The objects of the system orbiting the given one.
+    pub fn children(&self, object: &ObjectHandle) -> Vec<ObjectHandle> {
+        self.objects
+            .iter()
+            .filter(|o| {
+                o.borrow().orbit().origin().map_or(false, |origin| Rc::ptr_eq(&origin, object))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The other objects of the system orbiting the same object as the given
+    /// one, none if it orbits nothing.
+    pub fn siblings(&self, object: &ObjectHandle) -> Vec<ObjectHandle> {
+        match self.parent(object) {
+            Some(parent) => {
+                self.children(&parent)
+                    .into_iter()
+                    .filter(|sibling| !Rc::ptr_eq(sibling, object))
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Radius of the sphere of influence of the given object, from its
+    /// current distance to its parent. Infinite for the bodies orbiting
+    /// nothing, and None for the objects without "mass" or whose parent has
+    /// none.
+    pub fn sphere_of_influence(&self, object: &ObjectHandle) -> Option<f64> {
+        let object = object.borrow();
+        let mass = match object.register().get_float("mass") {
+            Some(mass) => *mass,
+            None => return None,
+        };
+        match object.orbit().origin() {
+            Some(parent) => {
+                let parent = parent.borrow();
+                parent.register().get_float("mass").map(|&parent_mass| {
+                    let distance = object.position().minus(&parent.position()).norm();
+                    sphere_of_influence(distance, mass, parent_mass)
+                })
+            }
+            None => Some(f64::INFINITY),
+        }
+    }
+
+    /// The body whose gravity dominates at the given position, i.e. the
+    /// deepest one in the orbits hierarchy whose sphere of influence contains
+    /// it. None if the system has no body with a "mass".
+    pub fn dominant_body(&self, position: Vector2f) -> Option<ObjectHandle> {
+        let contains = |body: &ObjectHandle| {
+            self.sphere_of_influence(body).map_or(false, |radius| {
+                position.minus(&body.borrow().position()).norm() <= radius
+            })
+        };
+        let root = self.objects
+                       .iter()
+                       .find(|o| o.borrow().orbit().origin().is_none() && contains(o));
+        let mut dominant = match root {
+            Some(root) => root.clone(),
+            None => return None,
+        };
+        // the spheres of influence of the siblings cannot overlap much, so
+        // take the closest one containing the position
+        loop {
+            let closest = self.children(&dominant)
+                              .into_iter()
+                              .filter(|child| contains(child))
+                              .map(|child| {
+                                  let distance = position.minus(&child.borrow().position()).norm();
+                                  (distance, child)
+                              })
+                              .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            match closest {
+                Some((_, child)) => dominant = child,
+                None => return Some(dominant),
+            }
+        }
+    }
+
+    /// Remove the given object from the system, and apply their orphan policy
+    /// to the objects orbiting it.
+    /// Return false if the object was not part of the system.
+    pub fn remove_object(&mut self, object: &ObjectHandle) -> bool {
+        let index = match self.objects.iter().position(|o| Rc::ptr_eq(o, object)) {
+            Some(index) => index,
+            None => return false,
+        };
+        let removed = self.objects.remove(index);
+
+        {
+            let removed_object = removed.borrow();
+            for object_handle in &self.objects {
+                let is_child = match object_handle.borrow().orbit().origin() {
+                    Some(origin) => Rc::ptr_eq(&origin, &removed),
+                    None => false,
+                };
+                if !is_child {
+                    continue;
+                }
+                let (orbit, policy) = {
+                    let child = object_handle.borrow();
+                    child.orbit().orphaned(child.position(), removed_object.orbit())
+                };
+                *object_handle.borrow_mut().orbit_mut() = orbit;
+                self.events.push(SystemEvent::Orphaned {
+                    object: object_handle.clone(),
+                    policy: policy,
+                });
+            }
+        }
+
+        self.events.push(SystemEvent::ObjectRemoved(removed));
+        self.update_order = self.compute_update_order()
+                                .unwrap_or_else(|_| (0..self.objects.len()).collect());
+        true
+    }
+
+    /// Return the events which happened since the last call.
+    pub fn drain_events(&mut self) -> Vec<SystemEvent> {
+        mem::replace(&mut self.events, Vec::new())
     }
 
     pub fn init(&mut self, r: &mut Rng) {
         for object_handle in &mut self.objects {
             object_handle.borrow_mut().init(r);
         }
+        self.rebuild_spatial_index();
     }
 
     pub fn update(&mut self, dt: f64) {
-        for object_handle in &mut self.objects {
-            object_handle.borrow_mut().update(dt);
+        self.integrate_free_objects(dt);
+        self.update_solar_flux();
+        for i in &self.update_order {
+            self.objects[*i].borrow_mut().update(dt);
+        }
+        self.handle_dropped_origins();
+        self.resolve_collisions();
+        self.rebuild_spatial_index();
+    }
+
+    /// The stars of the system, and their "luminosity".
+    fn lights(&self) -> Vec<(Disk, f64)> {
+        self.objects
+            .iter()
+            .filter_map(|object_handle| {
+                let object = object_handle.borrow();
+                match object.object_type() {
+                    ObjectType::Star => {
+                        object.register()
+                              .get_float("luminosity")
+                              .map(|&luminosity| {
+                                  (Disk::new(object.position(), object.bounding_radius()),
+                                   luminosity)
+                              })
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// The bodies casting shadows, i.e. the planets and the moons, along with
+    /// their index.
+    fn occluders(&self) -> Vec<(usize, Disk)> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(i, object_handle)| {
+                let object = object_handle.borrow();
+                match object.object_type() {
+                    ObjectType::Planet | ObjectType::Moon => {
+                        Some((i, Disk::new(object.position(), object.bounding_radius())))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Update the "solar_flux" and the "illumination" of the objects having
+    /// them : the flux they would receive from all the stars of the system
+    /// according to their "luminosity", and the fraction of it not hidden by
+    /// the other bodies.
+    fn update_solar_flux(&mut self) {
+        let lights = self.lights();
+        let occluders = self.occluders();
+        for (i, object_handle) in self.objects.iter().enumerate() {
+            let mut object = object_handle.borrow_mut();
+            let position = object.position();
+            let other_occluders: Vec<Disk> = occluders.iter()
+                                                      .filter(|&&(j, _)| i != j)
+                                                      .map(|&(_, disk)| disk)
+                                                      .collect();
+            let (mut flux, mut received) = (0.0, 0.0);
+            for &(ref light, luminosity) in &lights {
+                let light_flux = solar_flux(luminosity, position.minus(&light.position).norm());
+                flux += light_flux;
+                received += light_flux * illumination(position, light, &other_occluders);
+            }
+            let register = object.register_mut();
+            if let Some(solar_flux) = register.get_float_mut("solar_flux") {
+                *solar_flux = flux;
+            }
+            if let Some(illumination) = register.get_float_mut("illumination") {
+                *illumination = if flux > 0.0 { received / flux } else { 0.0 };
+            }
+        }
+    }
+
+    /// Draw the umbra cast by each body away from each star, fading out.
+    fn render_shadows(&self, c: Context, g: &mut G2d) {
+        let line_color = processed_color(SHADOW_COLOR);
+        for &(light, _) in &self.lights() {
+            for &(_, occluder) in &self.occluders() {
+                let center = Vec2::from(occluder.position);
+                let direction = (center - Vec2::from(light.position)).normalize();
+                let length = umbra_length(&light, &occluder)
+                                 .unwrap_or(MAX_SHADOW_LENGTH)
+                                 .min(MAX_SHADOW_LENGTH);
+                for i in 0..SHADOW_SEGMENTS {
+                    // the umbra narrows down to its tip
+                    let (t0, t1) = (i as f64 / SHADOW_SEGMENTS as f64,
+                                    (i + 1) as f64 / SHADOW_SEGMENTS as f64);
+                    let (start, end) = (center + direction * (occluder.radius + length * t0),
+                                        center + direction * (occluder.radius + length * t1));
+                    Line::new(line_color, occluder.radius * (1.0 - t0))
+                        .draw([start.x, start.y, end.x, end.y], &c.draw_state, c.transform, g);
+                }
+            }
+        }
+    }
+
+    /// Apply the collision outcome to the parts currently colliding, each
+    /// part taking part in at most one collision per update.
+    fn resolve_collisions(&mut self) {
+        let outcome = match self.collisions {
+            Some(outcome) => outcome,
+            None => return,
+        };
+        let mut items = Vec::new();
+        let mut bodies = Vec::new();
+        for object_handle in &self.objects {
+            let object = object_handle.borrow();
+            // A ring is an annulus around its parent, not the disk of its
+            // bounding radius, so it neither collides nor is picked.
+            if object.object_type() == ObjectType::Ring {
+                continue;
+            }
+            let parts = object.parts();
+            if parts.is_empty() {
+                items.push(SpatialItem::Object(object_handle.clone()));
+                bodies.push(CollisionBody::kinematic(object.position(),
+                                                     object.orbit().velocity(),
+                                                     object.bounding_radius()));
+            }
+            for part in parts {
+                items.push(SpatialItem::Part(object_handle.clone(), part.id));
+                bodies.push(CollisionBody::new(part.position,
+                                               part.velocity,
+                                               part.radius,
+                                               part.radius.powi(2)));
+            }
+        }
+
+        let mut collided = vec![false; bodies.len()];
+        let mut replacements: Vec<(ObjectHandle, usize, Vec<ObjectPart>)> = Vec::new();
+        for collision in detect_collisions(&bodies) {
+            let (i, j) = (collision.first, collision.second);
+            if collided[i] || collided[j] {
+                continue;
+            }
+            collided[i] = true;
+            collided[j] = true;
+            let result = outcome.resolve(&collision, &bodies[i], &bodies[j]);
+            for (k, replacing) in vec![(i, result.first), (j, result.second)] {
+                if let SpatialItem::Part(ref object_handle, part) = items[k] {
+                    let parts = replacing.into_iter()
+                                         .map(|body| {
+                                             ObjectPart {
+                                                 id: part,
+                                                 position: body.position,
+                                                 velocity: body.velocity,
+                                                 radius: body.radius,
+                                             }
+                                         })
+                                         .collect();
+                    replacements.push((object_handle.clone(), part, parts));
+                }
+            }
+            self.events.push(SystemEvent::Collision {
+                first: items[i].clone(),
+                second: items[j].clone(),
+                impact_velocity: collision.impact_velocity,
+            });
+        }
+
+        for object_handle in &self.objects {
+            let object_replacements: Vec<(usize, Vec<ObjectPart>)> =
+                replacements.iter()
+                            .filter(|&&(ref object, _, _)| Rc::ptr_eq(object, object_handle))
+                            .map(|&(_, part, ref parts)| (part, parts.clone()))
+                            .collect();
+            if !object_replacements.is_empty() {
+                object_handle.borrow_mut().replace_parts(object_replacements);
+            }
+        }
+    }
+
+    fn rebuild_spatial_index(&mut self) {
+        self.spatial_index.clear();
+        for object_handle in &self.objects {
+            let object = object_handle.borrow();
+            if object.object_type() == ObjectType::Ring {
+                continue;
+            }
+            let parts = object.parts();
+            if parts.is_empty() {
+                self.spatial_index.insert(object.position(),
+                                          object.bounding_radius(),
+                                          SpatialItem::Object(object_handle.clone()));
+            }
+            for part in parts {
+                self.spatial_index.insert(part.position,
+                                          part.radius,
+                                          SpatialItem::Part(object_handle.clone(), part.id));
+            }
+        }
+    }
+
+    /// Apply their orphan policy to the objects whose origin was dropped
+    /// without going through 'remove_object'. Their origin being gone, they
+    /// cannot inherit their grandparent and are frozen instead.
+    fn handle_dropped_origins(&mut self) {
+        for object_handle in &self.objects {
+            if !object_handle.borrow().orbit().is_orphan() {
+                continue;
+            }
+            let (orbit, policy) = {
+                let object = object_handle.borrow();
+                let position = object.position();
+                object.orbit().orphaned(position, &Orbit::Fixed(position))
+            };
+            *object_handle.borrow_mut().orbit_mut() = orbit;
+            self.events.push(SystemEvent::Orphaned {
+                object: object_handle.clone(),
+                policy: policy,
+            });
+        }
+    }
+
+    /// Advance the objects in free flight by 'dt' seconds, the on-rails
+    /// objects being considered at their current position.
+    fn integrate_free_objects(&self, dt: f64) {
+        let mut bodies = Vec::new();
+        let mut free_objects = Vec::new();
+        for object_handle in &self.objects {
+            let object = object_handle.borrow();
+            let mass = object.register().get_float("mass").map_or(0.0, |mass| *mass);
+            match *object.orbit() {
+                Orbit::Free { position, velocity } => {
+                    free_objects.push((object_handle, bodies.len()));
+                    bodies.push(PointMass::new(mass, position, velocity));
+                }
+                _ if mass > 0.0 => bodies.push(PointMass::kinematic(mass, object.position())),
+                _ => (),
+            }
+        }
+        if free_objects.is_empty() {
+            return;
+        }
+
+        match self.gravity {
+            Some(ref gravity) => gravity.step(&mut bodies, dt),
+            None => {
+                for body in bodies.iter_mut().filter(|body| !body.kinematic) {
+                    body.position.0 += dt * body.velocity.0;
+                    body.position.1 += dt * body.velocity.1;
+                }
+            }
+        }
+        for (object_handle, i) in free_objects {
+            *object_handle.borrow_mut().orbit_mut() = Orbit::Free {
+                position: bodies[i].position,
+                velocity: bodies[i].velocity,
+            };
         }
     }
 
     pub fn render(&mut self, c: Context, g: &mut G2d) {
+        for object_handle in &self.objects {
+            object_handle.borrow().render_background(c, g);
+        }
         for object_handle in &mut self.objects {
             object_handle.borrow_mut().render(c, g);
         }
+        if self.shadows {
+            self.render_shadows(c, g);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::rc::Rc;
+
+    use rand::{SeedableRng, XorShiftRng};
+
+    use solar_rustlib::core::{ObjectType, ObjectVisuals, ObjectRegister};
+    use solar_rustlib::core::ObjectPropertyValue::Float;
+    use objects::{DefaultObjectBuilder, ObjectHandle, Orbit};
+    use super::*;
+
+    fn body(object_type: ObjectType, mass: f64, orbit: Orbit) -> ObjectHandle {
+        let mut register = ObjectRegister::new();
+        register.add_property("mass", Float(mass), "Mass of the body.");
+        let visuals = ObjectVisuals::circle(1.0, (255, 255, 255));
+        DefaultObjectBuilder::with_visuals(object_type, visuals)
+            .register(register)
+            .orbit(orbit)
+            .build()
+    }
+
+    fn is(object: Option<ObjectHandle>, expected: &ObjectHandle) -> bool {
+        object.map_or(false, |object| Rc::ptr_eq(&object, expected))
+    }
+
+    #[test]
+    fn test_hierarchy() {
+        let star = body(ObjectType::Star, 1e6, Orbit::Fixed((0.0, 0.0)));
+        let planet = body(ObjectType::Planet,
+                          1000.0,
+                          Orbit::new_kepler_orbit(1000.0, 0.0, &star).unwrap());
+        let other_planet = body(ObjectType::Planet,
+                                1000.0,
+                                Orbit::new_kepler_orbit(1000.0, PI, &star).unwrap());
+        let moon = body(ObjectType::Moon,
+                        1.0,
+                        Orbit::new_kepler_orbit(30.0, 0.0, &planet).unwrap());
+
+        let mut system = GameSystem::new("test");
+        for object in vec![&moon, &planet, &star, &other_planet] {
+            system.add_object(object.clone()).unwrap();
+        }
+        system.init(&mut XorShiftRng::from_seed([1, 2, 3, 4]));
+        system.update(0.0);
+
+        assert!(system.parent(&star).is_none());
+        assert!(is(system.parent(&planet), &star));
+        assert!(is(system.parent(&moon), &planet));
+        let children = system.children(&star);
+        assert_eq!(children.len(), 2);
+        assert!(children.iter().any(|c| Rc::ptr_eq(c, &planet)));
+        assert!(children.iter().any(|c| Rc::ptr_eq(c, &other_planet)));
+        assert!(system.children(&moon).is_empty());
+        let siblings = system.siblings(&planet);
+        assert_eq!(siblings.len(), 1);
+        assert!(Rc::ptr_eq(&siblings[0], &other_planet));
+        assert!(system.siblings(&star).is_empty());
+
+        // the planets' spheres of influence are about 63 units wide, and the
+        // moon's about 2
+        assert!(is(system.dominant_body((1030.5, 0.0)), &moon));
+        assert!(is(system.dominant_body((1010.0, 0.0)), &planet));
+        assert!(is(system.dominant_body((-1000.0, 20.0)), &other_planet));
+        assert!(is(system.dominant_body((500.0, 0.0)), &star));
+        assert!(GameSystem::new("empty").dominant_body((0.0, 0.0)).is_none());
     }
 }
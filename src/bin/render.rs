@@ -2,6 +2,7 @@ use piston_window::{types, Context, G2d};
 
 use solar_rustlib::core::{ObjectVisuals, Color};
 use objects::DefaultObjectDrawFn;
+use textures::TextureHandle;
 
 
 const CIRCLE_BORDER_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
@@ -43,3 +44,14 @@ pub fn draw_fn_from_visuals(visuals: &ObjectVisuals) -> DefaultObjectDrawFn {
         }
     }
 }
+
+/// Build the function displaying the given texture centered on the object's
+/// position and stretched to the given width, like a circle 'ObjectVisuals'.
+pub fn draw_fn_from_texture(texture: TextureHandle, width: f64) -> DefaultObjectDrawFn {
+    use graphics::image::Image;
+
+    let image = Image::new().rect([width / -2.0, width / -2.0, width, width]);
+    Box::new(move |c: Context, g: &mut G2d| {
+        image.draw(&*texture, &c.draw_state, c.transform, g);
+    })
+}
@@ -3,14 +3,18 @@ use std::path::Path;
 use std::sync::Arc;
 use std::collections::HashMap;
 
-use gfx_device_gl::Resources;
+use gfx_device_gl::{Resources, Factory};
 use piston_window::*;
 
+use solar_rustlib::generator::SurfaceImage;
+
 pub type TextureHandle = Arc<Texture<Resources>>;
 
 /// A HashMap-based texture store.
 pub struct TextureStore {
     textures: HashMap<String, TextureHandle>,
+    /// Used to create the textures generated during the game.
+    factory: Factory,
 }
 
 impl TextureStore {
@@ -23,6 +27,34 @@ impl TextureStore {
             .clone()
     }
 
+    /// Get the texture from its filename, if it was loaded.
+    pub fn find<S: Into<String>>(&self, key: S) -> Option<TextureHandle> {
+        self.textures.get(&key.into()).cloned()
+    }
+
+    /// Create a texture from the given RGBA image and store it under the given
+    /// key, replacing any texture previously stored with the same key.
+    pub fn register<S: Into<String>>(&mut self,
+                                     key: S,
+                                     image: &SurfaceImage)
+                                     -> Result<TextureHandle, String> {
+        let key = key.into();
+        let texture = try!(Texture::create(&mut self.factory,
+                                           Format::Rgba8,
+                                           &image.pixels,
+                                           [image.width, image.height],
+                                           &TextureSettings::new())
+                               .map_err(|e| {
+                                   format!("TextureStore : could not create the texture \"{}\" : \
+                                            {:?}",
+                                           key,
+                                           e)
+                               }));
+        let handle = Arc::new(texture);
+        self.textures.insert(key, handle.clone());
+        Ok(handle)
+    }
+
     /// Create a new TextureStore instance and load all the *.png images in the
     /// given assets folder.
     /// Each texture will be stored with their filename set as their unique key.
@@ -58,6 +90,9 @@ impl TextureStore {
             }
         }
 
-        TextureStore { textures: store }
+        TextureStore {
+            textures: store,
+            factory: window.factory.clone(),
+        }
     }
 }
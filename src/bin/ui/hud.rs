@@ -16,8 +16,12 @@ pub struct ResourceWidget<T> {
     common: CommonBuilder,
     /// Pointer to the icon image.
     icon: Arc<T>,
+    /// Name of the resource, displayed before the amount.
+    maybe_name: Option<String>,
     /// Amount of resources to display.
     maybe_amount: Option<f64>,
+    /// Unit of the amount, displayed after it.
+    maybe_unit: Option<String>,
     /// Unique styling for the ResourceWidget.
     pub style: Style,
 }
@@ -58,13 +62,17 @@ impl<T> ResourceWidget<T> {
         ResourceWidget {
             common: CommonBuilder::new(),
             icon: texture,
+            maybe_name: None,
             maybe_amount: None,
+            maybe_unit: None,
             style: Style::new(),
         }
     }
 
     builder_methods!{
+        pub with_name { maybe_name = Some(String) }
         pub with_amount { maybe_amount = Some(f64) }
+        pub with_unit { maybe_unit = Some(String) }
     }
 }
 
@@ -132,12 +140,18 @@ impl<T> Widget for ResourceWidget<T>
         let label_idx = state.view().label_idx.get(&mut ui);
         let color = self.style.label_color(ui.theme());
         let font_size = self.style.label_font_size(ui.theme());
-        let label = match self.maybe_amount {
+        let mut label = match self.maybe_amount {
             Some(amount) => format!("{:.0}", amount),
             None => "#NA!".into(),
         };
+        if let Some(ref unit) = self.maybe_unit {
+            label = format!("{} {}", label, unit);
+        }
+        if let Some(ref name) = self.maybe_name {
+            label = format!("{} : {}", name, label);
+        }
         Text::new(&label[..])
-            .middle_of(rectangle_idx)
+            .right_from(image_idx, 5.0)
             .graphics_for(idx)
             .color(color)
             .font_size(font_size)
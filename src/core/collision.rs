@@ -0,0 +1,357 @@
+use std::f64::consts::PI;
+
+use util::{SpatialIndex, Vector, Vector2f};
+
+/// Fraction of the impact velocity given to the fragments flying apart.
+const FRAGMENTS_SPREAD: f64 = 0.5;
+
+/// A circular body taking part in the collisions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollisionBody {
+    pub position: Vector2f,
+    pub velocity: Vector2f,
+    pub radius: f64,
+    pub mass: f64,
+    /// A kinematic body is not affected by the collisions : its motion is
+    /// driven from the outside (e.g. on rails).
+    pub kinematic: bool,
+}
+
+impl CollisionBody {
+    pub fn new(position: Vector2f, velocity: Vector2f, radius: f64, mass: f64) -> CollisionBody {
+        CollisionBody {
+            position: position,
+            velocity: velocity,
+            radius: radius,
+            mass: mass,
+            kinematic: false,
+        }
+    }
+
+    pub fn kinematic(position: Vector2f, velocity: Vector2f, radius: f64) -> CollisionBody {
+        CollisionBody {
+            position: position,
+            velocity: velocity,
+            radius: radius,
+            mass: 0.0,
+            kinematic: true,
+        }
+    }
+
+    fn inverse_mass(&self) -> f64 {
+        if self.kinematic || self.mass <= 0.0 {
+            0.0
+        } else {
+            1.0 / self.mass
+        }
+    }
+}
+
+/// A collision between two bodies, given by their indices.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collision {
+    pub first: usize,
+    pub second: usize,
+    /// Unit vector pointing from the first body to the second one.
+    pub normal: Vector2f,
+    /// Speed at which the bodies were closing on each other along the normal.
+    pub impact_velocity: f64,
+    /// Depth of the overlap of the bodies.
+    pub overlap: f64,
+}
+
+/// Find the overlapping bodies moving towards each other, in the order of
+/// their indices. Kinematic bodies never collide together.
+pub fn detect_collisions(bodies: &[CollisionBody]) -> Vec<Collision> {
+    let mean_radius = bodies.iter().map(|body| body.radius).sum::<f64>() /
+                      bodies.len().max(1) as f64;
+    let mut index = SpatialIndex::new((2.0 * mean_radius).max(1.0));
+    for (i, body) in bodies.iter().enumerate() {
+        index.insert(body.position, body.radius, i);
+    }
+
+    let mut collisions = Vec::new();
+    for (i, body) in bodies.iter().enumerate() {
+        let mut neighbours: Vec<usize> = index.within_radius(body.position, body.radius)
+                                              .into_iter()
+                                              .map(|entry| entry.value)
+                                              .filter(|j| *j > i)
+                                              .collect();
+        neighbours.sort();
+        for j in neighbours {
+            let other = &bodies[j];
+            if body.kinematic && other.kinematic {
+                continue;
+            }
+            let offset = other.position.minus(&body.position);
+            let distance = offset.norm();
+            let overlap = body.radius + other.radius - distance;
+            if overlap <= 0.0 {
+                continue;
+            }
+            let normal = if distance > 0.0 {
+                (offset.0 / distance, offset.1 / distance)
+            } else {
+                (1.0, 0.0)
+            };
+            let relative_velocity = body.velocity.minus(&other.velocity);
+            let impact_velocity = relative_velocity.0 * normal.0 + relative_velocity.1 * normal.1;
+            if impact_velocity <= 0.0 {
+                continue; // already moving apart
+            }
+            collisions.push(Collision {
+                first: i,
+                second: j,
+                normal: normal,
+                impact_velocity: impact_velocity,
+                overlap: overlap,
+            });
+        }
+    }
+    collisions
+}
+
+/// What becomes of two colliding bodies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CollisionOutcome {
+    /// The bodies bounce off each other, keeping the given fraction of their
+    /// relative speed along the normal (1 for an elastic collision).
+    Bounce { restitution: f64 },
+    /// The bodies merge into one, conserving mass, momentum and area.
+    /// A kinematic body absorbs the other one.
+    Merge,
+    /// The bodies break into 'pieces' fragments flying apart, conserving mass,
+    /// momentum and area. A kinematic body stays intact and only the other one
+    /// breaks. Fragments smaller than 'min_radius' are pulverized.
+    Fragment { pieces: usize, min_radius: f64 },
+}
+
+/// The bodies replacing two colliding ones (none if a body was destroyed).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollisionResult {
+    pub first: Vec<CollisionBody>,
+    pub second: Vec<CollisionBody>,
+}
+
+impl CollisionOutcome {
+    pub fn resolve(&self,
+                   collision: &Collision,
+                   first: &CollisionBody,
+                   second: &CollisionBody)
+                   -> CollisionResult {
+        match *self {
+            CollisionOutcome::Bounce { restitution } => {
+                let (first, second) = bounce(collision, first, second, restitution);
+                CollisionResult {
+                    first: vec![first],
+                    second: vec![second],
+                }
+            }
+            CollisionOutcome::Merge => {
+                if first.kinematic {
+                    CollisionResult {
+                        first: vec![first.clone()],
+                        second: vec![],
+                    }
+                } else if second.kinematic {
+                    CollisionResult {
+                        first: vec![],
+                        second: vec![second.clone()],
+                    }
+                } else {
+                    keep_in_heaviest(first, second, vec![merge(first, second)])
+                }
+            }
+            CollisionOutcome::Fragment { pieces, min_radius } => {
+                let source = if first.kinematic || second.kinematic {
+                    // the fragments fly off the intact body
+                    let (bounced_first, bounced_second) = bounce(collision, first, second, 0.5);
+                    if first.kinematic { bounced_second } else { bounced_first }
+                } else {
+                    merge(first, second)
+                };
+                let fragments = fragment(&source,
+                                         pieces,
+                                         min_radius,
+                                         collision.impact_velocity * FRAGMENTS_SPREAD,
+                                         collision.normal);
+                if first.kinematic {
+                    CollisionResult {
+                        first: vec![first.clone()],
+                        second: fragments,
+                    }
+                } else if second.kinematic {
+                    CollisionResult {
+                        first: fragments,
+                        second: vec![second.clone()],
+                    }
+                } else {
+                    keep_in_heaviest(first, second, fragments)
+                }
+            }
+        }
+    }
+}
+
+/// Result of a collision where all the remaining bodies replace the heaviest
+/// of the two colliding ones.
+fn keep_in_heaviest(first: &CollisionBody,
+                    second: &CollisionBody,
+                    bodies: Vec<CollisionBody>)
+                    -> CollisionResult {
+    if second.mass > first.mass {
+        CollisionResult {
+            first: vec![],
+            second: bodies,
+        }
+    } else {
+        CollisionResult {
+            first: bodies,
+            second: vec![],
+        }
+    }
+}
+
+fn bounce(collision: &Collision,
+          first: &CollisionBody,
+          second: &CollisionBody,
+          restitution: f64)
+          -> (CollisionBody, CollisionBody) {
+    let (mut first, mut second) = (first.clone(), second.clone());
+    let (w1, w2) = (first.inverse_mass(), second.inverse_mass());
+    if w1 + w2 <= 0.0 {
+        return (first, second);
+    }
+    let n = collision.normal;
+    let impulse = (1.0 + restitution) * collision.impact_velocity / (w1 + w2);
+    first.velocity = (first.velocity.0 - impulse * w1 * n.0, first.velocity.1 - impulse * w1 * n.1);
+    second.velocity = (second.velocity.0 + impulse * w2 * n.0,
+                       second.velocity.1 + impulse * w2 * n.1);
+    // separate the bodies
+    let (d1, d2) = (collision.overlap * w1 / (w1 + w2), collision.overlap * w2 / (w1 + w2));
+    first.position = (first.position.0 - d1 * n.0, first.position.1 - d1 * n.1);
+    second.position = (second.position.0 + d2 * n.0, second.position.1 + d2 * n.1);
+    (first, second)
+}
+
+fn merge(first: &CollisionBody, second: &CollisionBody) -> CollisionBody {
+    let mass = first.mass + second.mass;
+    let weighted = |a: Vector2f, b: Vector2f| {
+        ((first.mass * a.0 + second.mass * b.0) / mass,
+         (first.mass * a.1 + second.mass * b.1) / mass)
+    };
+    CollisionBody::new(weighted(first.position, second.position),
+                       weighted(first.velocity, second.velocity),
+                       first.radius.hypot(second.radius),
+                       mass)
+}
+
+/// Break the body into evenly spread fragments flying apart at the given
+/// speed, the first one along the given direction.
+fn fragment(body: &CollisionBody,
+            pieces: usize,
+            min_radius: f64,
+            speed: f64,
+            direction: Vector2f)
+            -> Vec<CollisionBody> {
+    if pieces < 2 {
+        return vec![body.clone()];
+    }
+    let radius = body.radius / (pieces as f64).sqrt();
+    if radius < min_radius {
+        return vec![];
+    }
+    let start = direction.1.atan2(direction.0);
+    (0..pieces)
+        .map(|k| {
+            let angle = start + 2.0 * PI * k as f64 / pieces as f64;
+            let (cos, sin) = (angle.cos(), angle.sin());
+            let distance = body.radius - radius;
+            CollisionBody::new((body.position.0 + distance * cos, body.position.1 + distance * sin),
+                               (body.velocity.0 + speed * cos, body.velocity.1 + speed * sin),
+                               radius,
+                               body.mass / pieces as f64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn momentum(bodies: &[CollisionBody]) -> (f64, f64) {
+        bodies.iter().fold((0.0, 0.0), |p, body| {
+            (p.0 + body.mass * body.velocity.0, p.1 + body.mass * body.velocity.1)
+        })
+    }
+
+    #[test]
+    fn test_collision_detection() {
+        let bodies = [CollisionBody::new((0.0, 0.0), (1.0, 0.0), 5.0, 1.0),
+                      CollisionBody::new((8.0, 0.0), (-1.0, 0.0), 5.0, 1.0),
+                      // moving apart
+                      CollisionBody::new((-8.0, 0.0), (-2.0, 0.0), 5.0, 1.0),
+                      CollisionBody::kinematic((100.0, 0.0), (0.0, 0.0), 20.0),
+                      CollisionBody::kinematic((120.0, 0.0), (-5.0, 0.0), 25.0),
+                      CollisionBody::new((100.0, 30.0), (0.0, -3.0), 15.0, 1.0)];
+        let collisions = detect_collisions(&bodies);
+        let pairs: Vec<(usize, usize)> = collisions.iter().map(|c| (c.first, c.second)).collect();
+        assert_eq!(pairs, vec![(0, 1), (3, 5), (4, 5)]);
+        assert_eq!(collisions[0].normal, (1.0, 0.0));
+        assert_eq!(collisions[0].impact_velocity, 2.0);
+        assert_eq!(collisions[0].overlap, 2.0);
+    }
+
+    #[test]
+    fn test_collision_outcomes() {
+        let (first, second) = (CollisionBody::new((0.0, 0.0), (3.0, 1.0), 4.0, 2.0),
+                               CollisionBody::new((6.0, 0.0), (-1.0, 0.0), 3.0, 1.0));
+        let collision = detect_collisions(&[first.clone(), second.clone()]).remove(0);
+        let before = momentum(&[first.clone(), second.clone()]);
+        let close = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).abs() + (a.1 - b.1).abs() < 1e-9;
+
+        // an elastic bounce conserves the momentum and the kinetic energy
+        let result = CollisionOutcome::Bounce { restitution: 1.0 }
+                         .resolve(&collision, &first, &second);
+        let bodies = [result.first[0].clone(), result.second[0].clone()];
+        assert!(close(momentum(&bodies), before));
+        let energy = |bodies: &[CollisionBody]| {
+            bodies.iter()
+                  .map(|b| b.mass * (b.velocity.0.powi(2) + b.velocity.1.powi(2)))
+                  .sum::<f64>()
+        };
+        assert!((energy(&bodies) - energy(&[first.clone(), second.clone()])).abs() < 1e-9);
+        assert!(detect_collisions(&bodies).is_empty());
+
+        let result = CollisionOutcome::Merge.resolve(&collision, &first, &second);
+        assert!(result.second.is_empty());
+        assert_eq!(result.first[0].mass, 3.0);
+        assert_eq!(result.first[0].radius, 5.0);
+        assert!(close(momentum(&result.first), before));
+
+        let result = CollisionOutcome::Fragment {
+                         pieces: 4,
+                         min_radius: 1.0,
+                     }
+                     .resolve(&collision, &first, &second);
+        assert_eq!(result.first.len(), 4);
+        assert!((result.first.iter().map(|b| b.mass).sum::<f64>() - 3.0).abs() < 1e-9);
+        assert!(close(momentum(&result.first), before));
+        assert!(CollisionOutcome::Fragment {
+                    pieces: 4,
+                    min_radius: 3.0,
+                }
+                .resolve(&collision, &first, &second)
+                .first
+                .is_empty());
+
+        // a kinematic body is left untouched
+        let planet = CollisionBody::kinematic((6.0, 0.0), (-1.0, 0.0), 3.0);
+        let result = CollisionOutcome::Merge.resolve(&collision, &first, &planet);
+        assert_eq!((result.first.len(), &result.second[0]), (0, &planet));
+        let result = CollisionOutcome::Bounce { restitution: 1.0 }
+                         .resolve(&collision, &first, &planet);
+        assert_eq!(result.second[0], planet);
+        assert_eq!(result.first[0].velocity, (-5.0, 1.0));
+    }
+}
@@ -0,0 +1,363 @@
+use rand::Rng;
+
+use core::{ObjectType, ObjectRegister};
+use generator::{TypeGenerator, SamplerFunction, Distribution};
+
+/// The different ores which can be found in the bodies' deposits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OreType {
+    Iron,
+    Nickel,
+    Silicates,
+    Carbon,
+    WaterIce,
+    /// Frozen gases, e.g. carbon dioxide and ammonia.
+    Volatiles,
+    Platinum,
+}
+
+impl OreType {
+    pub fn all() -> [OreType; 7] {
+        use self::OreType::*;
+        [Iron, Nickel, Silicates, Carbon, WaterIce, Volatiles, Platinum]
+    }
+
+    /// Identifier of the ore used in the registers' keys.
+    pub fn key(&self) -> &'static str {
+        match *self {
+            OreType::Iron => "iron",
+            OreType::Nickel => "nickel",
+            OreType::Silicates => "silicates",
+            OreType::Carbon => "carbon",
+            OreType::WaterIce => "water_ice",
+            OreType::Volatiles => "volatiles",
+            OreType::Platinum => "platinum",
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match *self {
+            OreType::Iron => "Iron",
+            OreType::Nickel => "Nickel",
+            OreType::Silicates => "Silicates",
+            OreType::Carbon => "Carbon",
+            OreType::WaterIce => "Water ice",
+            OreType::Volatiles => "Volatiles",
+            OreType::Platinum => "Platinum",
+        }
+    }
+
+    fn abundance_key(&self) -> String {
+        format!("deposit_{}_abundance", self.key())
+    }
+
+    /// Key of the ore's remaining reserve in the registers.
+    pub fn reserve_key(&self) -> String {
+        format!("deposit_{}_reserve", self.key())
+    }
+}
+
+/// The deposit of a single ore inside a body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deposit {
+    pub ore: OreType,
+    /// Fraction of the body's composition made of the ore.
+    pub abundance: f64,
+    /// Total amount of the ore that can be mined from the body.
+    pub reserve: f64,
+}
+
+/// The resource composition of a body.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Composition {
+    pub deposits: Vec<Deposit>,
+}
+
+impl Composition {
+    pub fn deposit(&self, ore: OreType) -> Option<&Deposit> {
+        self.deposits.iter().find(|deposit| deposit.ore == ore)
+    }
+
+    pub fn total_reserve(&self) -> f64 {
+        self.deposits.iter().fold(0.0, |sum, deposit| sum + deposit.reserve)
+    }
+
+    /// The same composition with all the reserves multiplied by 'factor', e.g.
+    /// to describe a fraction of the body.
+    pub fn scaled(&self, factor: f64) -> Composition {
+        Composition {
+            deposits: self.deposits
+                          .iter()
+                          .map(|deposit| {
+                              Deposit {
+                                  reserve: deposit.reserve * factor,
+                                  ..deposit.clone()
+                              }
+                          })
+                          .collect(),
+        }
+    }
+
+    /// Add the deposits of another body to this composition, e.g. to describe
+    /// a group of bodies as a whole.
+    /// The abundances are averaged, weighted by the total reserves.
+    pub fn merge(&mut self, other: &Composition) {
+        let (own_total, other_total) = (self.total_reserve(), other.total_reserve());
+        if own_total + other_total <= 0.0 {
+            return;
+        }
+        let own_weight = own_total / (own_total + other_total);
+        let other_weight = 1.0 - own_weight;
+
+        for deposit in &mut self.deposits {
+            deposit.abundance *= own_weight;
+        }
+        for other_deposit in &other.deposits {
+            let index = self.deposits.iter().position(|d| d.ore == other_deposit.ore);
+            match index {
+                Some(i) => {
+                    self.deposits[i].abundance += other_deposit.abundance * other_weight;
+                    self.deposits[i].reserve += other_deposit.reserve;
+                }
+                None => {
+                    self.deposits.push(Deposit {
+                        ore: other_deposit.ore,
+                        abundance: other_deposit.abundance * other_weight,
+                        reserve: other_deposit.reserve,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Store the deposits inside the given register : the abundances as
+    /// constants and the reserves as properties, since they deplete.
+    pub fn store(&self, register: &mut ObjectRegister) {
+        use core::ObjectPropertyValue::*;
+
+        for deposit in &self.deposits {
+            let (abundance_key, reserve_key) = (deposit.ore.abundance_key(),
+                                                deposit.ore.reserve_key());
+            register.add_constant(abundance_key.clone(),
+                                  Float(deposit.abundance),
+                                  "Fraction of the body's composition made of the ore.".into());
+            register.add_property(reserve_key.clone(),
+                                  Float(deposit.reserve),
+                                  "Amount of the ore left to be mined.".into());
+            register.set_display_name(abundance_key,
+                                      format!("{} abundance", deposit.ore.display_name()));
+            register.set_display_name(reserve_key,
+                                      format!("{} reserve", deposit.ore.display_name()));
+        }
+    }
+
+    /// Read back the deposits stored in the given register.
+    pub fn from_register(register: &ObjectRegister) -> Composition {
+        let deposits = OreType::all()
+                           .iter()
+                           .filter_map(|ore| {
+                               match (register.get_float(ore.abundance_key()),
+                                      register.get_float(ore.reserve_key())) {
+                                   (Some(abundance), Some(reserve)) => {
+                                       Some(Deposit {
+                                           ore: *ore,
+                                           abundance: *abundance,
+                                           reserve: *reserve,
+                                       })
+                                   }
+                                   _ => None,
+                               }
+                           })
+                           .collect();
+        Composition { deposits: deposits }
+    }
+}
+
+/// Mine up to 'amount' of the given ore from the deposits stored in the
+/// register, and return the amount actually extracted.
+pub fn mine(register: &mut ObjectRegister, ore: OreType, amount: f64) -> f64 {
+    // the mutable getter would create the missing deposits on demand
+    if register.get_float(ore.reserve_key()).is_none() {
+        return 0.0;
+    }
+    match register.get_float_mut(ore.reserve_key()) {
+        Some(reserve) => {
+            let extracted = amount.max(0.0).min(*reserve);
+            *reserve -= extracted;
+            extracted
+        }
+        None => 0.0,
+    }
+}
+
+/// Generates the composition of a body, with the relative weight of each ore
+/// and the body's total reserve sampled independently.
+pub struct CompositionGenerator<R: Rng> {
+    ores: Vec<(OreType, SamplerFunction<f64, R>)>,
+    reserve: SamplerFunction<f64, R>,
+}
+
+impl<R: Rng> CompositionGenerator<R> {
+    /// Add an ore to the generated compositions, with the given distribution
+    /// for its relative weight.
+    pub fn ore(mut self, ore: OreType, weight: SamplerFunction<f64, R>) -> Self {
+        self.ores.push((ore, weight));
+        self
+    }
+
+    builder_setters!(options => {}; others => { reserve { reserve: SamplerFunction<f64, R> } });
+
+    /// The default composition distributions of each type of object.
+    /// Stars, stations and satellites have no deposits at all.
+    pub fn for_object_type(object_type: &ObjectType) -> Self {
+        let normal = |mean: f64, std_dev: f64| {
+            Distribution::Normal {
+                mean: mean,
+                std_dev: std_dev,
+            }
+            .to_sampler()
+        };
+        let range = |low: f64, high: f64| {
+            Distribution::Range {
+                low: low,
+                high: high,
+            }
+            .to_sampler()
+        };
+
+        let generator = CompositionGenerator::default();
+        match *object_type {
+            ObjectType::Asteroid => {
+                generator.ore(OreType::Iron, normal(0.3, 0.1))
+                         .ore(OreType::Nickel, normal(0.1, 0.05))
+                         .ore(OreType::Silicates, normal(0.4, 0.1))
+                         .ore(OreType::Carbon, range(0.0, 0.1))
+                         .ore(OreType::Platinum, range(0.0, 0.03))
+                         .reserve(range(50.0, 500.0))
+            }
+            ObjectType::Comet => {
+                generator.ore(OreType::WaterIce, normal(0.5, 0.1))
+                         .ore(OreType::Volatiles, normal(0.2, 0.05))
+                         .ore(OreType::Silicates, normal(0.2, 0.05))
+                         .ore(OreType::Carbon, range(0.0, 0.1))
+                         .reserve(range(2000.0, 8000.0))
+            }
+            ObjectType::Moon => {
+                generator.ore(OreType::Silicates, normal(0.6, 0.1))
+                         .ore(OreType::Iron, normal(0.15, 0.05))
+                         .ore(OreType::WaterIce, range(0.0, 0.3))
+                         .reserve(range(5000.0, 20000.0))
+            }
+            ObjectType::Planet => {
+                generator.ore(OreType::Silicates, normal(0.5, 0.1))
+                         .ore(OreType::Iron, normal(0.3, 0.05))
+                         .ore(OreType::Nickel, normal(0.05, 0.02))
+                         .ore(OreType::Carbon, range(0.0, 0.1))
+                         .ore(OreType::WaterIce, range(0.0, 0.1))
+                         .reserve(range(50000.0, 200000.0))
+            }
+            ObjectType::Star | ObjectType::Station | ObjectType::Satellite |
+            ObjectType::Ring => generator,
+        }
+    }
+}
+
+impl<R: Rng> TypeGenerator<R> for CompositionGenerator<R> {
+    type Generated = Composition;
+
+    fn default() -> CompositionGenerator<R> {
+        CompositionGenerator {
+            ores: Vec::new(),
+            reserve: Distribution::Constant(0.0).into(),
+        }
+    }
+
+    fn generate(&mut self, rng: &mut R) -> Result<Composition, String> {
+        let weights: Vec<(OreType, f64)> = self.ores
+                                               .iter()
+                                               .map(|&(ore, ref weight)| (ore, weight(rng).max(0.0)))
+                                               .collect();
+        let total_weight = weights.iter().fold(0.0, |sum, &(_, weight)| sum + weight);
+        if total_weight <= 0.0 {
+            return Err("CompositionGenerator : no ore with a positive abundance".into());
+        }
+        let reserve = (self.reserve)(rng).max(0.0);
+
+        Ok(Composition {
+            deposits: weights.into_iter()
+                             .filter(|&(_, weight)| weight > 0.0)
+                             .map(|(ore, weight)| {
+                                 Deposit {
+                                     ore: ore,
+                                     abundance: weight / total_weight,
+                                     reserve: reserve * weight / total_weight,
+                                 }
+                             })
+                             .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use super::*;
+    use core::{ObjectType, ObjectRegister};
+    use generator::TypeGenerator;
+
+    #[test]
+    fn test_composition_generation_and_mining() {
+        let mut rng = XorShiftRng::from_seed([3, 1, 4, 1]);
+        let mut generator = CompositionGenerator::for_object_type(&ObjectType::Asteroid);
+        let composition = generator.generate(&mut rng).unwrap();
+
+        let total_abundance = composition.deposits
+                                         .iter()
+                                         .fold(0.0, |sum, deposit| sum + deposit.abundance);
+        assert!((total_abundance - 1.0).abs() < 1e-9);
+        assert!(composition.total_reserve() >= 50.0 && composition.total_reserve() <= 500.0);
+
+        let mut register = ObjectRegister::new();
+        composition.store(&mut register);
+        assert_eq!(Composition::from_register(&register), composition);
+
+        let iron = composition.deposit(OreType::Iron).unwrap().reserve;
+        assert_eq!(mine(&mut register, OreType::Iron, 1.0), 1.0);
+        assert_eq!(mine(&mut register, OreType::Iron, iron), iron - 1.0);
+        assert_eq!(mine(&mut register, OreType::Iron, 1.0), 0.0);
+        assert_eq!(mine(&mut register, OreType::WaterIce, 1.0), 0.0);
+        let depleted = Composition::from_register(&register);
+        assert_eq!(depleted.deposit(OreType::Iron).unwrap().reserve, 0.0);
+
+        assert!(CompositionGenerator::for_object_type(&ObjectType::Star)
+                    .generate(&mut rng)
+                    .is_err());
+    }
+
+    #[test]
+    fn test_composition_merge() {
+        let mut a = Composition {
+            deposits: vec![Deposit {
+                               ore: OreType::Iron,
+                               abundance: 1.0,
+                               reserve: 300.0,
+                           }],
+        };
+        let b = Composition {
+            deposits: vec![Deposit {
+                               ore: OreType::Carbon,
+                               abundance: 1.0,
+                               reserve: 100.0,
+                           }],
+        };
+        a.merge(&b);
+        assert_eq!(a.total_reserve(), 400.0);
+        assert_eq!(a.deposit(OreType::Iron).unwrap().abundance, 0.75);
+        assert_eq!(a.deposit(OreType::Carbon).unwrap().abundance, 0.25);
+
+        let half = a.scaled(0.5);
+        assert_eq!(half.total_reserve(), 200.0);
+        assert_eq!(half.deposit(OreType::Iron).unwrap().abundance, 0.75);
+    }
+}
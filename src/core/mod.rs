@@ -1,5 +1,18 @@
+pub mod collision;
+pub mod composition;
 pub mod object;
+pub mod orbital;
+pub mod physics;
 mod properties;
+pub mod resources;
+pub mod shadow;
+pub mod star;
 
+pub use self::collision::{CollisionBody, CollisionOutcome, detect_collisions};
+pub use self::composition::{Composition, CompositionGenerator, Deposit, OreType};
 pub use self::object::{ObjectType, ObjectVisuals, Color};
+pub use self::physics::{Gravity, PointMass, GAME_GRAVITATIONAL_CONSTANT};
 pub use self::properties::{ObjectRegister, ObjectPropertyValue};
+pub use self::resources::{ResourceKind, ResourceRegistry, ResourceRole, ResourceType};
+pub use self::shadow::{Disk, Shadow, illumination};
+pub use self::star::{Star, StarGenerator, SpectralClass};
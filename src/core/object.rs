@@ -5,8 +5,10 @@ pub enum ObjectType {
     Planet,
     Moon,
     Asteroid,
+    Comet,
     Station,
     Satellite,
+    Ring,
 }
 
 /// A color encoded as 4 8-bit RGBA channels.
@@ -41,4 +43,15 @@ impl ObjectVisuals {
             color: [rgb.0, rgb.1, rgb.2, 255],
         }
     }
+
+    /// Radius of the circle centered on the object's position and enclosing
+    /// its drawn shape.
+    pub fn bounding_radius(&self) -> f64 {
+        match *self {
+            // the circle's radius is drawn as its width
+            ObjectVisuals::Circle { radius, .. } => radius / 2.0,
+            // the square is drawn from its top-left corner
+            ObjectVisuals::Square { size, .. } => size * ::std::f64::consts::SQRT_2,
+        }
+    }
 }
@@ -0,0 +1,438 @@
+use std::f64::consts::PI;
+
+use core::physics::GAME_GRAVITATIONAL_CONSTANT;
+use util::{Vector2f, Vec2};
+
+/// Duration of a game day, in seconds of simulation.
+pub const GAME_DAY_DURATION: f64 = 1.0;
+
+/// Standard gravitational parameter (G·M) of a body of the given mass, in
+/// solar masses.
+pub fn gravitational_parameter(mass: f64) -> f64 {
+    GAME_GRAVITATIONAL_CONSTANT * mass
+}
+
+/// Angular speed, in radians per second, of a circular orbit at the given
+/// altitude around a body of the given mass (Kepler's third law).
+pub fn circular_angular_speed(parent_mass: f64, altitude: f64) -> f64 {
+    (gravitational_parameter(parent_mass) / altitude.powi(3)).sqrt()
+}
+
+/// Linear speed, in game units per second, of a circular orbit at the given
+/// altitude around a body of the given mass.
+pub fn circular_speed(parent_mass: f64, altitude: f64) -> f64 {
+    (gravitational_parameter(parent_mass) / altitude).sqrt()
+}
+
+/// Period, in game days, of a circular orbit at the given altitude around a
+/// body of the given mass.
+pub fn orbital_period(parent_mass: f64, altitude: f64) -> f64 {
+    2.0 * PI / circular_angular_speed(parent_mass, altitude) / GAME_DAY_DURATION
+}
+
+/// Angular speed, in radians per second, of an orbit with the given period in
+/// game days.
+pub fn angular_speed_from_period(period: f64) -> f64 {
+    2.0 * PI / (period * GAME_DAY_DURATION)
+}
+
+/// Radius of the sphere of influence of a body of mass 'mass' orbiting at
+/// 'semi_major_axis' a parent of mass 'parent_mass' (Laplace's definition),
+/// i.e. the region where the motion of a small object is best described as
+/// orbiting the body rather than its parent.
+pub fn sphere_of_influence(semi_major_axis: f64, mass: f64, parent_mass: f64) -> f64 {
+    semi_major_axis * (mass / parent_mass).powf(0.4)
+}
+
+/// Period, in game days, after which two bodies orbiting the same parent on
+/// circular orbits at the given altitudes find themselves in the same relative
+/// position. None if the altitudes are the same.
+pub fn synodic_period(parent_mass: f64, altitude1: f64, altitude2: f64) -> Option<f64> {
+    let relative_speed = (circular_angular_speed(parent_mass, altitude1) -
+                          circular_angular_speed(parent_mass, altitude2))
+                             .abs();
+    if relative_speed > 0.0 {
+        Some(2.0 * PI / relative_speed / GAME_DAY_DURATION)
+    } else {
+        None
+    }
+}
+
+/// A Hohmann transfer : the most efficient two-impulse transfer between two
+/// coplanar circular orbits around the same parent, along half an ellipse
+/// tangent to both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HohmannTransfer {
+    pub parent_mass: f64,
+    pub departure_altitude: f64,
+    pub arrival_altitude: f64,
+    /// Speed change needed to leave the departure orbit, in game units per
+    /// second.
+    pub departure_delta_v: f64,
+    /// Speed change needed to circularize on the arrival orbit, in game units
+    /// per second.
+    pub arrival_delta_v: f64,
+    /// Duration of the transfer, in game days.
+    pub duration: f64,
+    /// Angle by which the arrival body must lead the departing one at launch,
+    /// in radians along the direction of motion.
+    pub phase_angle: f64,
+}
+
+impl HohmannTransfer {
+    pub fn new(parent_mass: f64,
+               departure_altitude: f64,
+               arrival_altitude: f64)
+               -> Result<HohmannTransfer, String> {
+        if parent_mass <= 0.0 || departure_altitude <= 0.0 || arrival_altitude <= 0.0 {
+            return Err(format!("HohmannTransfer : invalid transfer from {} to {} around a \
+                                mass of {}",
+                               departure_altitude,
+                               arrival_altitude,
+                               parent_mass));
+        }
+        let mu = gravitational_parameter(parent_mass);
+        let (r1, r2) = (departure_altitude, arrival_altitude);
+        let departure_delta_v = (mu / r1).sqrt() * ((2.0 * r2 / (r1 + r2)).sqrt() - 1.0);
+        let arrival_delta_v = (mu / r2).sqrt() * (1.0 - (2.0 * r1 / (r1 + r2)).sqrt());
+        let duration = PI * ((r1 + r2).powi(3) / (8.0 * mu)).sqrt();
+        let phase_angle = PI - circular_angular_speed(parent_mass, r2) * duration;
+
+        Ok(HohmannTransfer {
+            parent_mass: parent_mass,
+            departure_altitude: r1,
+            arrival_altitude: r2,
+            departure_delta_v: departure_delta_v.abs(),
+            arrival_delta_v: arrival_delta_v.abs(),
+            duration: duration / GAME_DAY_DURATION,
+            phase_angle: phase_angle,
+        })
+    }
+
+    pub fn total_delta_v(&self) -> f64 {
+        self.departure_delta_v + self.arrival_delta_v
+    }
+
+    /// Time to wait, in game days, before the next launch window given the
+    /// current angles of the departing and arrival bodies, in radians along
+    /// the direction of motion. None if the bodies share the same orbit.
+    pub fn next_window(&self, departure_angle: f64, arrival_angle: f64) -> Option<f64> {
+        let departure_speed = circular_angular_speed(self.parent_mass, self.departure_altitude);
+        let arrival_speed = circular_angular_speed(self.parent_mass, self.arrival_altitude);
+        let relative_speed = arrival_speed - departure_speed;
+        if relative_speed == 0.0 {
+            return None;
+        }
+        // the phase evolves at the relative speed until it matches the transfer's
+        let phase = arrival_angle - departure_angle;
+        let remaining = if relative_speed < 0.0 {
+            phase - self.phase_angle
+        } else {
+            self.phase_angle - phase
+        };
+        let remaining = ((remaining % (2.0 * PI)) + 2.0 * PI) % (2.0 * PI);
+        Some(remaining / relative_speed.abs() / GAME_DAY_DURATION)
+    }
+}
+
+/// Maximum number of Newton iterations when solving Kepler's equation.
+const KEPLER_MAX_ITERATIONS: usize = 50;
+
+/// Solve Kepler's equation 'M = E - e·sin(E)', returning the eccentric anomaly
+/// E of the given mean anomaly M on an orbit of eccentricity e below 1.
+pub fn eccentric_anomaly(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let m = mean_anomaly % (2.0 * PI);
+    // Newton's method, starting from π for the highly eccentric orbits
+    let mut e = if eccentricity < 0.8 { m } else { PI };
+    for _ in 0..KEPLER_MAX_ITERATIONS {
+        let delta = (e - eccentricity * e.sin() - m) / (1.0 - eccentricity * e.cos());
+        e -= delta;
+        if delta.abs() < 1e-12 {
+            break;
+        }
+    }
+    e
+}
+
+/// An elliptic orbit around a body, with the parent at one of its foci.
+/// The object moves counterclockwise, its mean anomaly growing at the mean
+/// motion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EllipticOrbit {
+    pub parent_mass: f64,
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    /// Angle of the periapsis, in radians.
+    pub periapsis_angle: f64,
+}
+
+impl EllipticOrbit {
+    /// The orbit with the given closest and farthest altitudes from the parent.
+    pub fn from_apsides(parent_mass: f64,
+                        periapsis: f64,
+                        apoapsis: f64,
+                        periapsis_angle: f64)
+                        -> Result<EllipticOrbit, String> {
+        if parent_mass <= 0.0 || periapsis <= 0.0 || apoapsis < periapsis {
+            return Err(format!("EllipticOrbit : invalid apsides {} and {} around a mass of {}",
+                               periapsis,
+                               apoapsis,
+                               parent_mass));
+        }
+        Ok(EllipticOrbit {
+            parent_mass: parent_mass,
+            semi_major_axis: (periapsis + apoapsis) / 2.0,
+            eccentricity: (apoapsis - periapsis) / (apoapsis + periapsis),
+            periapsis_angle: periapsis_angle,
+        })
+    }
+
+    pub fn periapsis(&self) -> f64 {
+        self.semi_major_axis * (1.0 - self.eccentricity)
+    }
+
+    pub fn apoapsis(&self) -> f64 {
+        self.semi_major_axis * (1.0 + self.eccentricity)
+    }
+
+    /// Mean angular speed, in radians per second.
+    pub fn mean_motion(&self) -> f64 {
+        circular_angular_speed(self.parent_mass, self.semi_major_axis)
+    }
+
+    /// Period, in game days.
+    pub fn period(&self) -> f64 {
+        orbital_period(self.parent_mass, self.semi_major_axis)
+    }
+
+    /// Position relative to the parent at the given mean anomaly, in radians.
+    pub fn position(&self, mean_anomaly: f64) -> Vector2f {
+        let (a, e) = (self.semi_major_axis, self.eccentricity);
+        let anomaly = eccentric_anomaly(mean_anomaly, e);
+        let perifocal = Vec2::new(a * (anomaly.cos() - e),
+                                  a * (1.0 - e * e).sqrt() * anomaly.sin());
+        perifocal.rotate(self.periapsis_angle).into()
+    }
+
+    /// Velocity, in game units per second, at the given mean anomaly.
+    pub fn velocity(&self, mean_anomaly: f64) -> Vector2f {
+        let (a, e) = (self.semi_major_axis, self.eccentricity);
+        let anomaly = eccentric_anomaly(mean_anomaly, e);
+        let anomaly_rate = self.mean_motion() / (1.0 - e * anomaly.cos());
+        let perifocal = Vec2::new(-a * anomaly.sin(), a * (1.0 - e * e).sqrt() * anomaly.cos()) *
+                        anomaly_rate;
+        perifocal.rotate(self.periapsis_angle).into()
+    }
+}
+
+/// The five points where a small object keeps its position relative to two
+/// bodies orbiting each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LagrangePoint {
+    /// Between the bodies.
+    L1,
+    /// Beyond the secondary body.
+    L2,
+    /// Beyond the primary body, opposite the secondary one.
+    L3,
+    /// Leading the secondary body by 60°.
+    L4,
+    /// Trailing the secondary body by 60°.
+    L5,
+}
+
+impl LagrangePoint {
+    /// Coordinates of the point relative to the primary body, in units of the
+    /// distance between the bodies, with the x axis pointing towards the
+    /// secondary body and the y axis along its direction of motion.
+    /// - 'mass_ratio' is the mass of the secondary body over the total mass.
+    pub fn coordinates(&self, mass_ratio: f64) -> Vector2f {
+        let mu = mass_ratio.max(0.0).min(1.0);
+        // the collinear points are found in the co-rotating frame centered on
+        // the barycenter, where the primary lies at -mu and the secondary at 1 - mu
+        let x = match *self {
+            LagrangePoint::L1 => collinear_equilibrium(mu, -mu, 1.0 - mu),
+            LagrangePoint::L2 => collinear_equilibrium(mu, 1.0 - mu, 2.0),
+            LagrangePoint::L3 => collinear_equilibrium(mu, -2.0, -mu),
+            LagrangePoint::L4 => return (0.5, 3f64.sqrt() / 2.0),
+            LagrangePoint::L5 => return (0.5, -3f64.sqrt() / 2.0),
+        };
+        (x + mu, 0.0)
+    }
+}
+
+/// Solve by bisection, between 'low' and 'high', the position on the axis of
+/// the bodies where the gravity and the centrifugal force cancel out.
+fn collinear_equilibrium(mu: f64, low: f64, high: f64) -> f64 {
+    let net_force = |x: f64| {
+        let (d1, d2) = (x + mu, x - 1.0 + mu);
+        x - (1.0 - mu) * d1 / d1.abs().powi(3) - mu * d2 / d2.abs().powi(3)
+    };
+    // the force increases with x between the bodies' singularities
+    let (mut low, mut high) = (low, high);
+    for _ in 0..64 {
+        let middle = (low + high) / 2.0;
+        if net_force(middle) < 0.0 {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::physics::{Gravity, PointMass};
+
+    #[test]
+    fn test_kepler_third_law() {
+        let (inner, outer) = (100.0, 400.0);
+        assert!(circular_angular_speed(1.0, inner) > circular_angular_speed(1.0, outer));
+
+        // T² ∝ a³
+        let ratio = (orbital_period(1.0, outer) / orbital_period(1.0, inner)).powi(2);
+        assert!((ratio - (outer / inner).powi(3)).abs() < 1e-9);
+        // T² ∝ 1 / M
+        let ratio = (orbital_period(1.0, inner) / orbital_period(4.0, inner)).powi(2);
+        assert!((ratio - 4.0).abs() < 1e-9);
+
+        let speed = circular_angular_speed(1.0, inner);
+        assert!((angular_speed_from_period(orbital_period(1.0, inner)) - speed).abs() < 1e-12);
+        assert!((circular_speed(1.0, inner) - speed * inner).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sphere_of_influence() {
+        // the Earth's, of about 0.0062 AU
+        let earth = sphere_of_influence(1.0, 3.003e-6, 1.0);
+        assert!((earth - 0.0062).abs() < 0.0001);
+        assert_eq!(sphere_of_influence(100.0, 1.0, 1.0), 100.0);
+        // within the Hill sphere for the small bodies
+        let (mass, parent_mass) = (1e-4, 1.0);
+        assert!(sphere_of_influence(100.0, mass, parent_mass) <
+                100.0 * (mass / (3.0 * parent_mass)).cbrt());
+    }
+
+    #[test]
+    fn test_hohmann_transfer() {
+        let (r1, r2) = (100.0, 250.0);
+        let transfer = HohmannTransfer::new(1.0, r1, r2).unwrap();
+        let inward = HohmannTransfer::new(1.0, r2, r1).unwrap();
+        assert!((transfer.total_delta_v() - inward.total_delta_v()).abs() < 1e-9);
+        assert!((transfer.duration - inward.duration).abs() < 1e-9);
+        assert!(HohmannTransfer::new(1.0, 0.0, r2).is_err());
+
+        // a body given the departure impulse reaches the arrival altitude when
+        // expected, on the far side of the parent
+        let gravity = Gravity::game();
+        let speed = circular_speed(1.0, r1) + transfer.departure_delta_v;
+        let mut bodies = vec![PointMass::kinematic(1.0, (0.0, 0.0)),
+                              PointMass::new(0.0, (r1, 0.0), (0.0, speed))];
+        let dt = 0.001;
+        let steps = (transfer.duration * GAME_DAY_DURATION / dt).round() as usize;
+        for _ in 0..steps {
+            gravity.step(&mut bodies, dt);
+        }
+        let (x, y) = bodies[1].position;
+        assert!(((x * x + y * y).sqrt() - r2).abs() < 0.01 * r2);
+        assert!(y.abs() < 0.01 * r2 && x < 0.0);
+        let arrival_speed = (bodies[1].velocity.0.powi(2) + bodies[1].velocity.1.powi(2)).sqrt();
+        assert!((arrival_speed + transfer.arrival_delta_v - circular_speed(1.0, r2)).abs() <
+                0.01 * circular_speed(1.0, r2));
+    }
+
+    #[test]
+    fn test_hohmann_launch_window() {
+        let transfer = HohmannTransfer::new(1.0, 100.0, 250.0).unwrap();
+        assert_eq!(transfer.next_window(0.0, transfer.phase_angle).unwrap(), 0.0);
+
+        let (departure_angle, arrival_angle) = (0.3, 2.0);
+        let wait = transfer.next_window(departure_angle, arrival_angle).unwrap();
+        assert!(wait >= 0.0 && wait <= synodic_period(1.0, 100.0, 250.0).unwrap());
+        let t = wait * GAME_DAY_DURATION;
+        let phase = (arrival_angle + circular_angular_speed(1.0, 250.0) * t) -
+                    (departure_angle + circular_angular_speed(1.0, 100.0) * t);
+        let error = ((phase - transfer.phase_angle) % (2.0 * PI) + 2.0 * PI) % (2.0 * PI);
+        assert!(error < 1e-6 || 2.0 * PI - error < 1e-6);
+        assert!(HohmannTransfer::new(1.0, 100.0, 100.0).unwrap().next_window(0.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_elliptic_orbit() {
+        for &e in &[0.0, 0.5, 0.97] {
+            for i in 0..16 {
+                let m = i as f64 * 0.4;
+                let anomaly = eccentric_anomaly(m, e);
+                assert!((anomaly - e * anomaly.sin() - m % (2.0 * PI)).abs() < 1e-9);
+            }
+        }
+
+        let orbit = EllipticOrbit::from_apsides(1.0, 50.0, 450.0, 0.7).unwrap();
+        assert!((orbit.eccentricity - 0.8).abs() < 1e-12);
+        assert!((orbit.periapsis() - 50.0).abs() < 1e-9);
+        assert!((orbit.apoapsis() - 450.0).abs() < 1e-9);
+        assert!((orbit.period() - orbital_period(1.0, 250.0)).abs() < 1e-9);
+        assert!(EllipticOrbit::from_apsides(1.0, 100.0, 50.0, 0.0).is_err());
+
+        let distance = |m: f64| {
+            let (x, y) = orbit.position(m);
+            x.hypot(y)
+        };
+        assert!((distance(0.0) - 50.0).abs() < 1e-9 && (distance(PI) - 450.0).abs() < 1e-9);
+        let (x, y) = orbit.position(0.0);
+        assert!((y.atan2(x) - 0.7).abs() < 1e-9);
+        let mu = gravitational_parameter(1.0);
+        for i in 0..8 {
+            let m = i as f64 * 0.8;
+            // vis-viva equation, and the velocity as the position's derivative
+            let (vx, vy) = orbit.velocity(m);
+            let expected = mu * (2.0 / distance(m) - 1.0 / orbit.semi_major_axis);
+            assert!((vx * vx + vy * vy - expected).abs() < 1e-9 * expected);
+            let dm = 1e-6;
+            let (x1, y1) = orbit.position(m + dm);
+            let (x0, y0) = orbit.position(m - dm);
+            let dt = 2.0 * dm / orbit.mean_motion();
+            let error = ((x1 - x0) / dt - vx).abs() + ((y1 - y0) / dt - vy).abs();
+            assert!(error < 1e-4 * expected.sqrt());
+        }
+    }
+
+    #[test]
+    fn test_lagrange_points() {
+        let (primary_mass, secondary_mass, distance) = (1.0, 0.05, 100.0);
+        let mu = secondary_mass / (primary_mass + secondary_mass);
+        let (l1, l2, l3) = (LagrangePoint::L1.coordinates(mu).0,
+                            LagrangePoint::L2.coordinates(mu).0,
+                            LagrangePoint::L3.coordinates(mu).0);
+        assert!(0.0 < l1 && l1 < 1.0 && l2 > 1.0 && l3 < 0.0);
+        assert_eq!(LagrangePoint::L1.coordinates(0.0), (1.0, 0.0));
+
+        // in the frame co-rotating with the bodies, an object at any of the
+        // points is in equilibrium
+        let gravity = Gravity::new(GAME_GRAVITATIONAL_CONSTANT);
+        let angular_speed = circular_angular_speed(primary_mass + secondary_mass, distance);
+        let barycenter = mu * distance;
+        for point in &[LagrangePoint::L1,
+                       LagrangePoint::L2,
+                       LagrangePoint::L3,
+                       LagrangePoint::L4,
+                       LagrangePoint::L5] {
+            let (x, y) = point.coordinates(mu);
+            let position = (x * distance, y * distance);
+            let bodies = [PointMass::kinematic(primary_mass, (0.0, 0.0)),
+                          PointMass::kinematic(secondary_mass, (distance, 0.0)),
+                          PointMass::new(0.0, position, (0.0, 0.0))];
+            let acceleration = gravity.accelerations(&bodies)[2];
+            let centrifugal = ((position.0 - barycenter) * angular_speed.powi(2),
+                               position.1 * angular_speed.powi(2));
+            let residual = ((acceleration.0 + centrifugal.0).powi(2) +
+                            (acceleration.1 + centrifugal.1).powi(2))
+                               .sqrt();
+            assert!(residual < 1e-9 * centrifugal.0.hypot(centrifugal.1).max(1.0),
+                    "{:?} is not an equilibrium",
+                    point);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use util::Vector2f;
+
+/// Gravitational constant of the game's simulation, in game units³ per solar
+/// mass per second².
+/// Chosen so that a body orbiting a one solar mass star at 150 game units
+/// (i.e. one astronomical unit) does a full revolution in about 80 seconds.
+pub const GAME_GRAVITATIONAL_CONSTANT: f64 = 2.0e4;
+
+/// A body subject to (and source of) gravity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PointMass {
+    /// Mass, in solar masses. Massless bodies are only subject to gravity.
+    pub mass: f64,
+    pub position: Vector2f,
+    pub velocity: Vector2f,
+    /// A kinematic body attracts the others but is not moved by the
+    /// integration : its motion is driven from the outside (e.g. on rails).
+    pub kinematic: bool,
+}
+
+impl PointMass {
+    pub fn new(mass: f64, position: Vector2f, velocity: Vector2f) -> PointMass {
+        PointMass {
+            mass: mass,
+            position: position,
+            velocity: velocity,
+            kinematic: false,
+        }
+    }
+
+    pub fn kinematic(mass: f64, position: Vector2f) -> PointMass {
+        PointMass {
+            mass: mass,
+            position: position,
+            velocity: (0.0, 0.0),
+            kinematic: true,
+        }
+    }
+}
+
+/// Newtonian N-body gravity, integrated with the (symplectic) velocity Verlet
+/// scheme so that the energy of the bodies does not drift over time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gravity {
+    pub gravitational_constant: f64,
+    /// Length added in quadrature to the distances, bounding the forces during
+    /// close encounters.
+    pub softening: f64,
+}
+
+impl Gravity {
+    pub fn new(gravitational_constant: f64) -> Gravity {
+        Gravity {
+            gravitational_constant: gravitational_constant,
+            softening: 0.0,
+        }
+    }
+
+    /// Gravity with the game's gravitational constant.
+    pub fn game() -> Gravity {
+        Gravity {
+            gravitational_constant: GAME_GRAVITATIONAL_CONSTANT,
+            softening: 1.0,
+        }
+    }
+
+    /// Gravitational acceleration undergone by each body.
+    pub fn accelerations(&self, bodies: &[PointMass]) -> Vec<Vector2f> {
+        let epsilon2 = self.softening * self.softening;
+        let mut accelerations = vec![(0.0, 0.0); bodies.len()];
+        for i in 0..bodies.len() {
+            for j in i + 1..bodies.len() {
+                let dx = bodies[j].position.0 - bodies[i].position.0;
+                let dy = bodies[j].position.1 - bodies[i].position.1;
+                let d2 = dx * dx + dy * dy + epsilon2;
+                if d2 == 0.0 {
+                    continue;
+                }
+                let f = self.gravitational_constant / (d2 * d2.sqrt());
+                accelerations[i].0 += f * bodies[j].mass * dx;
+                accelerations[i].1 += f * bodies[j].mass * dy;
+                accelerations[j].0 -= f * bodies[i].mass * dx;
+                accelerations[j].1 -= f * bodies[i].mass * dy;
+            }
+        }
+        accelerations
+    }
+
+    /// Advance the non-kinematic bodies by 'dt' seconds.
+    pub fn step(&self, bodies: &mut [PointMass], dt: f64) {
+        let accelerations = self.accelerations(bodies);
+        for (body, a) in bodies.iter_mut().zip(&accelerations) {
+            if body.kinematic {
+                continue;
+            }
+            body.velocity.0 += 0.5 * dt * a.0;
+            body.velocity.1 += 0.5 * dt * a.1;
+            body.position.0 += dt * body.velocity.0;
+            body.position.1 += dt * body.velocity.1;
+        }
+        let accelerations = self.accelerations(bodies);
+        for (body, a) in bodies.iter_mut().zip(&accelerations) {
+            if body.kinematic {
+                continue;
+            }
+            body.velocity.0 += 0.5 * dt * a.0;
+            body.velocity.1 += 0.5 * dt * a.1;
+        }
+    }
+
+    pub fn kinetic_energy(&self, bodies: &[PointMass]) -> f64 {
+        bodies.iter().fold(0.0, |sum, body| {
+            let v2 = body.velocity.0 * body.velocity.0 + body.velocity.1 * body.velocity.1;
+            sum + 0.5 * body.mass * v2
+        })
+    }
+
+    pub fn potential_energy(&self, bodies: &[PointMass]) -> f64 {
+        let epsilon2 = self.softening * self.softening;
+        let mut energy = 0.0;
+        for i in 0..bodies.len() {
+            for j in i + 1..bodies.len() {
+                let dx = bodies[j].position.0 - bodies[i].position.0;
+                let dy = bodies[j].position.1 - bodies[i].position.1;
+                let d = (dx * dx + dy * dy + epsilon2).sqrt();
+                if d > 0.0 {
+                    energy -= self.gravitational_constant * bodies[i].mass * bodies[j].mass / d;
+                }
+            }
+        }
+        energy
+    }
+
+    pub fn total_energy(&self, bodies: &[PointMass]) -> f64 {
+        self.kinetic_energy(bodies) + self.potential_energy(bodies)
+    }
+
+    pub fn momentum(&self, bodies: &[PointMass]) -> Vector2f {
+        bodies.iter().fold((0.0, 0.0), |p, body| {
+            (p.0 + body.mass * body.velocity.0, p.1 + body.mass * body.velocity.1)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_conservation() {
+        let gravity = Gravity::new(1.0);
+        // eccentric binary, plus a light third body on a wide orbit
+        let mut bodies = vec![PointMass::new(1.0, (0.0, 0.0), (0.0, -0.1)),
+                              PointMass::new(0.1, (1.0, 0.0), (0.0, 0.8)),
+                              PointMass::new(0.01, (-4.0, 0.0), (0.0, -0.48))];
+        let initial_energy = gravity.total_energy(&bodies);
+        let initial_momentum = gravity.momentum(&bodies);
+
+        let mut max_drift: f64 = 0.0;
+        for _ in 0..100000 {
+            gravity.step(&mut bodies, 0.001);
+            let energy = gravity.total_energy(&bodies);
+            max_drift = max_drift.max(((energy - initial_energy) / initial_energy).abs());
+        }
+        assert!(max_drift < 1e-4, "energy drift : {}", max_drift);
+
+        let momentum = gravity.momentum(&bodies);
+        assert!((momentum.0 - initial_momentum.0).abs() < 1e-9);
+        assert!((momentum.1 - initial_momentum.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_kinematic_attractor() {
+        let gravity = Gravity::game();
+        let r = 150.0;
+        let v = (GAME_GRAVITATIONAL_CONSTANT / r).sqrt();
+        let mut bodies = vec![PointMass::kinematic(1.0, (0.0, 0.0)),
+                              PointMass::new(0.0, (r, 0.0), (0.0, v))];
+        let period = 2.0 * ::std::f64::consts::PI * r / v;
+        let steps = (period * 60.0) as usize;
+        for _ in 0..steps {
+            gravity.step(&mut bodies, 1.0 / 60.0);
+            let distance = (bodies[1].position.0.powi(2) + bodies[1].position.1.powi(2)).sqrt();
+            assert!((distance - r).abs() < 0.01 * r);
+        }
+        assert_eq!(bodies[0].position, (0.0, 0.0));
+        // back near its starting point after a full revolution
+        assert!((bodies[1].position.0 - r).abs() < 0.02 * r);
+        assert!(bodies[1].position.1.abs() < 0.05 * r);
+    }
+}
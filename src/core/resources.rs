@@ -0,0 +1,292 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use core::ObjectRegister;
+
+/// How a resource is accounted for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceKind {
+    /// The resource accumulates in stock, up to the given capacity.
+    Storable { capacity: f64 },
+    /// The resource is only produced and consumed, never stored.
+    Flow,
+}
+
+/// The game mechanic supplying a resource.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceRole {
+    /// Extracted from the asteroids.
+    Mining,
+    /// Harvested from the active comets.
+    Harvesting,
+    /// Produced by the solar panels of the stations.
+    SolarPower,
+}
+
+impl ResourceRole {
+    pub fn all() -> [ResourceRole; 3] {
+        use self::ResourceRole::*;
+        [Mining, Harvesting, SolarPower]
+    }
+
+    /// Identifier of the role in the resources data.
+    pub fn key(&self) -> &'static str {
+        match *self {
+            ResourceRole::Mining => "mining",
+            ResourceRole::Harvesting => "harvesting",
+            ResourceRole::SolarPower => "solar_power",
+        }
+    }
+}
+
+/// A type of resource the objects can produce, store or exchange.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResourceType {
+    /// Unique identifier, also used as the key of the resource in the
+    /// registers.
+    pub id: String,
+    pub display_name: String,
+    /// Filename of the icon displayed in the HUD, if any.
+    pub icon: Option<String>,
+    pub unit: String,
+    /// Game mechanic supplying the resource, if any.
+    pub role: Option<ResourceRole>,
+    pub kind: ResourceKind,
+}
+
+impl ResourceType {
+    pub fn is_storable(&self) -> bool {
+        match self.kind {
+            ResourceKind::Storable { .. } => true,
+            ResourceKind::Flow => false,
+        }
+    }
+
+    /// Register key of the maximum amount that can be stored.
+    pub fn max_key(&self) -> String {
+        format!("{}_max", self.id)
+    }
+
+    /// Register key of the amount produced, or consumed if negative, by
+    /// seconds.
+    pub fn rate_key(&self) -> String {
+        format!("{}_rate", self.id)
+    }
+}
+
+/// The resource types of the game, loaded from data so that new resources can
+/// be added without touching the code.
+///
+/// Each non-empty line of the data describes a resource, with fields separated
+/// by '|' : id, display name, icon (or '-'), unit, role (see 'ResourceRole',
+/// or '-'), kind ('storable' or 'flow') and, for the storable resources, the
+/// capacity. Everything after a '#' is ignored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceRegistry {
+    types: Vec<ResourceType>,
+}
+
+impl ResourceRegistry {
+    pub fn new() -> ResourceRegistry {
+        Default::default()
+    }
+
+    pub fn from_file(path: &Path) -> Result<ResourceRegistry, String> {
+        let mut text = String::new();
+        try!(File::open(path)
+                 .and_then(|mut file| file.read_to_string(&mut text))
+                 .map_err(|e| {
+                     format!("ResourceRegistry : could not read \"{}\" : {}",
+                             path.display(),
+                             e)
+                 }));
+        ResourceRegistry::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<ResourceRegistry, String> {
+        let mut registry = ResourceRegistry::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let resource = try!(parse_resource(line)
+                                    .map_err(|e| {
+                                        format!("ResourceRegistry : line {} : {}", number + 1, e)
+                                    }));
+            try!(registry.add(resource));
+        }
+        Ok(registry)
+    }
+
+    /// Add a resource type, failing if its id or its role is already taken.
+    pub fn add(&mut self, resource: ResourceType) -> Result<(), String> {
+        if self.get(&resource.id).is_some() {
+            return Err(format!("ResourceRegistry : duplicate resource \"{}\"", resource.id));
+        }
+        if let Some(role) = resource.role {
+            if self.with_role(role).is_ok() {
+                return Err(format!("ResourceRegistry : several resources for the role \"{}\"",
+                                   role.key()));
+            }
+        }
+        self.types.push(resource);
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ResourceType> {
+        self.types.iter().find(|resource| resource.id == id)
+    }
+
+    /// The resource supplied by the given game mechanic, failing if the data
+    /// defines none.
+    pub fn with_role(&self, role: ResourceRole) -> Result<&ResourceType, String> {
+        self.types
+            .iter()
+            .find(|resource| resource.role == Some(role))
+            .ok_or_else(|| {
+                format!("ResourceRegistry : no resource for the role \"{}\"", role.key())
+            })
+    }
+
+    /// The resource types, in the order they were defined.
+    pub fn types(&self) -> &[ResourceType] {
+        &self.types
+    }
+
+    /// Add the properties tracking every resource to the register : the
+    /// stock and its capacity for the storable resources, and the production
+    /// rate for all of them.
+    pub fn fill_register(&self, register: &mut ObjectRegister) {
+        use core::ObjectPropertyValue::*;
+
+        for resource in &self.types {
+            if let ResourceKind::Storable { capacity } = resource.kind {
+                register.add_property(resource.id.clone(),
+                                      Float(0.0),
+                                      format!("Current amount of {} in stock.",
+                                              resource.display_name.to_lowercase()));
+                register.set_display_name(resource.id.clone(), resource.display_name.clone());
+                register.add_property(resource.max_key(),
+                                      Float(capacity),
+                                      format!("Maximum amount of {} that can be stored.",
+                                              resource.display_name.to_lowercase()));
+                register.set_display_name(resource.max_key(),
+                                          format!("{} maximum stock", resource.display_name));
+            }
+            register.add_property(resource.rate_key(),
+                                  Float(0.0),
+                                  format!("Amount of {} produced in situ by seconds.",
+                                          resource.display_name.to_lowercase()));
+            register.set_display_name(resource.rate_key(),
+                                      format!("{} production rate", resource.display_name));
+        }
+    }
+
+    /// Accumulate the production of the storable resources during 'dt'
+    /// seconds, keeping their stocks between zero and their capacities.
+    pub fn update_register(&self, register: &mut ObjectRegister, dt: f64) {
+        for resource in self.types.iter().filter(|resource| resource.is_storable()) {
+            let rate = register.get_float(resource.rate_key()).cloned().unwrap_or(0.0);
+            let max = match register.get_float(resource.max_key()) {
+                Some(&max) => max,
+                None => continue,
+            };
+            register.get_float_mut(resource.id.clone())
+                    .map(|v| *v = (*v + rate * dt).max(0.0).min(max));
+        }
+    }
+}
+
+fn parse_resource(line: &str) -> Result<ResourceType, String> {
+    let fields: Vec<&str> = line.split('|').map(|field| field.trim()).collect();
+    if fields.len() < 6 || fields.len() > 7 {
+        return Err(format!("expected 6 or 7 fields, found {}", fields.len()));
+    }
+    if fields[0].is_empty() {
+        return Err("missing resource id".to_string());
+    }
+    let role = match fields[4] {
+        "" | "-" => None,
+        key => {
+            match ResourceRole::all().iter().find(|role| role.key() == key) {
+                Some(role) => Some(*role),
+                None => return Err(format!("unknown resource role \"{}\"", key)),
+            }
+        }
+    };
+    let kind = match (fields[5], fields.get(6)) {
+        ("storable", Some(capacity)) => {
+            let capacity = try!(capacity.parse::<f64>().map_err(|_| {
+                format!("invalid capacity \"{}\"", capacity)
+            }));
+            ResourceKind::Storable { capacity: capacity }
+        }
+        ("storable", None) => return Err("missing capacity of storable resource".to_string()),
+        ("flow", None) => ResourceKind::Flow,
+        ("flow", Some(_)) => return Err("flow resources have no capacity".to_string()),
+        (kind, _) => return Err(format!("unknown resource kind \"{}\"", kind)),
+    };
+    Ok(ResourceType {
+        id: fields[0].to_string(),
+        display_name: fields[1].to_string(),
+        icon: match fields[2] {
+            "" | "-" => None,
+            icon => Some(icon.to_string()),
+        },
+        unit: fields[3].to_string(),
+        role: role,
+        kind: kind,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ObjectRegister;
+
+    #[test]
+    fn test_resource_registry() {
+        let registry = ResourceRegistry::parse("# id | name | icon | unit | role | kind | \
+                                                capacity\n\
+                                                minerals | Minerals | minerals_icon.png | t | \
+                                                mining | storable | 1000\n\
+                                                \n\
+                                                research | Research points | - | pts | - | flow \
+                                                # not stored\n")
+                           .unwrap();
+        assert_eq!(registry.types().len(), 2);
+        let minerals = registry.get("minerals").unwrap();
+        assert_eq!(minerals.icon, Some("minerals_icon.png".to_string()));
+        assert_eq!(minerals.role, Some(ResourceRole::Mining));
+        assert_eq!(minerals.kind, ResourceKind::Storable { capacity: 1000.0 });
+        let research = registry.get("research").unwrap();
+        assert_eq!(research.icon, None);
+        assert_eq!(research.role, None);
+        assert_eq!(research.kind, ResourceKind::Flow);
+        assert_eq!(registry.with_role(ResourceRole::Mining), Ok(minerals));
+        assert!(registry.with_role(ResourceRole::SolarPower).is_err());
+
+        let mut register = ObjectRegister::new();
+        registry.fill_register(&mut register);
+        assert_eq!(register.get_float("minerals_max"), Some(&1000.0));
+        assert!(register.get_float("research").is_none());
+        *register.get_float_mut("minerals_rate").unwrap() = 300.0;
+        registry.update_register(&mut register, 2.0);
+        assert_eq!(register.get_float("minerals"), Some(&600.0));
+        registry.update_register(&mut register, 2.0);
+        assert_eq!(register.get_float("minerals"), Some(&1000.0));
+
+        assert!(ResourceRegistry::parse("a | A | - | t | - | storable").is_err());
+        assert!(ResourceRegistry::parse("a | A | - | t | - | flow | 10").is_err());
+        assert!(ResourceRegistry::parse("a | A | - | t | - | solid | 10").is_err());
+        assert!(ResourceRegistry::parse("a | A | - | t | - | storable | lots").is_err());
+        assert!(ResourceRegistry::parse("a | A | - | t | drilling | flow").is_err());
+        assert!(ResourceRegistry::parse("a | A | - | t | - | flow\na | B | - | t | - | flow")
+                    .is_err());
+        assert!(ResourceRegistry::parse("a | A | - | t | mining | flow\nb | B | - | t | mining \
+                                         | flow")
+                    .is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use util::{Vector2f, Vec2};
+
+/// A circular body, casting a shadow or emitting light.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Disk {
+    pub position: Vector2f,
+    pub radius: f64,
+}
+
+impl Disk {
+    pub fn new(position: Vector2f, radius: f64) -> Disk {
+        Disk {
+            position: position,
+            radius: radius,
+        }
+    }
+}
+
+/// Where a point lies relative to the shadows cast by the bodies around it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shadow {
+    /// The whole light source is visible.
+    Lit,
+    /// The light source is partially hidden.
+    Penumbra,
+    /// The light source is entirely hidden.
+    Umbra,
+}
+
+impl Shadow {
+    pub fn from_illumination(illumination: f64) -> Shadow {
+        if illumination >= 1.0 {
+            Shadow::Lit
+        } else if illumination <= 0.0 {
+            Shadow::Umbra
+        } else {
+            Shadow::Penumbra
+        }
+    }
+}
+
+/// Fraction, between 0 and 1, of the light source visible from the target
+/// position past the given occluding bodies, as the fraction of the source's
+/// apparent width which is not hidden.
+pub fn illumination(target: Vector2f, light: &Disk, occluders: &[Disk]) -> f64 {
+    let target = Vec2::from(target);
+    let to_light = Vec2::from(light.position) - target;
+    let light_distance = to_light.length();
+    if light_distance <= light.radius {
+        return 1.0;
+    }
+    let light_half_width = (light.radius / light_distance).asin();
+
+    // the hidden angular intervals, relative to the direction of the light
+    let mut hidden: Vec<(f64, f64)> = Vec::new();
+    for occluder in occluders {
+        let to_occluder = Vec2::from(occluder.position) - target;
+        let distance = to_occluder.length();
+        if distance <= occluder.radius {
+            return 0.0;
+        }
+        // only the bodies in front of the light source
+        if distance >= light_distance || to_light.dot(to_occluder) <= 0.0 {
+            continue;
+        }
+        let half_width = (occluder.radius / distance).asin();
+        let angle = to_light.angle_to(to_occluder);
+        let (start, end) = ((angle - half_width).max(-light_half_width),
+                            (angle + half_width).min(light_half_width));
+        if start < end {
+            hidden.push((start, end));
+        }
+    }
+
+    // length of the union of the intervals
+    hidden.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let mut hidden_width = 0.0;
+    let mut covered_until = -light_half_width;
+    for (start, end) in hidden {
+        if end > covered_until {
+            hidden_width += end - start.max(covered_until);
+            covered_until = end;
+        }
+    }
+    (1.0 - hidden_width / (2.0 * light_half_width)).max(0.0).min(1.0)
+}
+
+/// Length of the umbra cast by the occluder away from the light source, from
+/// the occluder's center. None if the umbra never ends, the occluder being at
+/// least as large as the source.
+pub fn umbra_length(light: &Disk, occluder: &Disk) -> Option<f64> {
+    if occluder.radius >= light.radius {
+        return None;
+    }
+    let distance = Vec2::from(occluder.position).distance(Vec2::from(light.position));
+    Some(distance * occluder.radius / (light.radius - occluder.radius))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_illumination() {
+        let sun = Disk::new((0.0, 0.0), 10.0);
+        let planet = Disk::new((100.0, 0.0), 5.0);
+        let moon = Disk::new((101.0, 3.0), 4.0);
+
+        // in the umbra, the penumbra, or lit
+        let umbra = umbra_length(&sun, &planet).unwrap();
+        assert!((umbra - 100.0).abs() < 1e-9);
+        assert_eq!(illumination((110.0, 0.0), &sun, &[planet]), 0.0);
+        let partial = illumination((110.0, 5.0), &sun, &[planet]);
+        assert!(partial > 0.0 && partial < 1.0);
+        assert_eq!(Shadow::from_illumination(partial), Shadow::Penumbra);
+        assert_eq!(illumination((110.0, 30.0), &sun, &[planet]), 1.0);
+        // beyond the end of the umbra, in the antumbra
+        let expected = 1.0 - (5.0f64 / 150.0).asin() / (10.0f64 / 250.0).asin();
+        assert!((illumination((250.0, 0.0), &sun, &[planet]) - expected).abs() < 1e-9);
+        let antumbra = illumination((300.0, 0.0), &sun, &[planet]);
+        assert!(antumbra > 0.0 && antumbra < 1.0);
+
+        // nothing is hidden by the bodies behind the target
+        assert_eq!(illumination((50.0, 0.0), &sun, &[planet]), 1.0);
+        assert_eq!(illumination((100.0, 0.0), &sun, &[planet]), 0.0);
+
+        // the overlapping shadows are not counted twice
+        let both = illumination((110.0, 5.0), &sun, &[planet, moon]);
+        assert!(both <= partial && both <= illumination((110.0, 5.0), &sun, &[moon]));
+        assert!(both >= 0.0);
+        assert_eq!(illumination((110.0, 5.0), &sun, &[planet, planet]), partial);
+        assert!(umbra_length(&planet, &sun).is_none());
+    }
+}
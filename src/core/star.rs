@@ -0,0 +1,317 @@
+use std::fmt;
+
+use rand::Rng;
+
+use core::{ObjectVisuals, ObjectRegister};
+use generator::{TypeGenerator, SamplerFunction, Distribution, weighted_choice};
+
+/// Game units corresponding to one astronomical unit, before the compression
+/// applied by 'au_to_game_units'.
+pub const GAME_UNITS_PER_AU: f64 = 150.0;
+
+/// Effective temperature of the Sun, in Kelvin.
+const SOLAR_TEMPERATURE: f64 = 5772.0;
+
+/// Displayed size of a star of one solar radius.
+const SOLAR_VISUAL_RADIUS: f64 = 75.0;
+
+/// Convert a distance in astronomical units to game units.
+/// The real distances span several orders of magnitude between spectral
+/// classes, so they are compressed with a fourth root to stay displayable and
+/// to leave room for the moons of the planets around the dimmest stars.
+pub fn au_to_game_units(au: f64) -> f64 {
+    GAME_UNITS_PER_AU * au.powf(0.25)
+}
+
+/// Inverse of 'au_to_game_units'.
+pub fn game_units_to_au(distance: f64) -> f64 {
+    (distance / GAME_UNITS_PER_AU).powi(4)
+}
+
+/// Flux received at the given distance in game units from a star of the given
+/// luminosity, in solar constants (i.e. relative to the Earth's).
+/// The flux falls off with the square of the distance in game units, and not
+/// in astronomical units which would make it vanish far too quickly with the
+/// compression of 'au_to_game_units'. The distance is clamped to one game unit
+/// to keep the flux finite.
+/// Both models agree at one astronomical unit only : the flux is a gameplay
+/// quantity, whereas the temperatures and the habitable zone stay physical
+/// and go through 'game_units_to_au'. The flux model would otherwise put the
+/// habitable zone of the red dwarfs inside the stars.
+pub fn solar_flux(luminosity: f64, distance: f64) -> f64 {
+    luminosity * (GAME_UNITS_PER_AU / distance.max(1.0)).powi(2)
+}
+
+/// The Morgan-Keenan spectral classes of main sequence stars.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpectralClass {
+    O,
+    B,
+    A,
+    F,
+    G,
+    K,
+    M,
+}
+
+impl SpectralClass {
+    /// All the spectral classes, from the hottest to the coolest.
+    pub fn all() -> [SpectralClass; 7] {
+        use self::SpectralClass::*;
+        [O, B, A, F, G, K, M]
+    }
+
+    /// Fraction of the main sequence stars belonging to the class.
+    pub fn frequency(&self) -> f64 {
+        match *self {
+            SpectralClass::O => 0.00003,
+            SpectralClass::B => 0.0013,
+            SpectralClass::A => 0.006,
+            SpectralClass::F => 0.03,
+            SpectralClass::G => 0.076,
+            SpectralClass::K => 0.121,
+            SpectralClass::M => 0.7645,
+        }
+    }
+
+    /// (hottest, coolest) effective temperatures of the class, in Kelvin.
+    pub fn temperature_range(&self) -> (f64, f64) {
+        match *self {
+            SpectralClass::O => (50000.0, 30000.0),
+            SpectralClass::B => (30000.0, 10000.0),
+            SpectralClass::A => (10000.0, 7500.0),
+            SpectralClass::F => (7500.0, 6000.0),
+            SpectralClass::G => (6000.0, 5200.0),
+            SpectralClass::K => (5200.0, 3700.0),
+            SpectralClass::M => (3700.0, 2400.0),
+        }
+    }
+
+    /// (heaviest, lightest) masses of the class, in solar masses.
+    pub fn mass_range(&self) -> (f64, f64) {
+        match *self {
+            SpectralClass::O => (60.0, 16.0),
+            SpectralClass::B => (16.0, 2.1),
+            SpectralClass::A => (2.1, 1.4),
+            SpectralClass::F => (1.4, 1.04),
+            SpectralClass::G => (1.04, 0.8),
+            SpectralClass::K => (0.8, 0.45),
+            SpectralClass::M => (0.45, 0.08),
+        }
+    }
+
+    /// The in-game RGB color of the stars of the class.
+    pub fn color(&self) -> (u8, u8, u8) {
+        match *self {
+            SpectralClass::O => (110, 140, 255),
+            SpectralClass::B => (150, 175, 255),
+            SpectralClass::A => (210, 220, 255),
+            SpectralClass::F => (255, 250, 220),
+            SpectralClass::G => (255, 255, 0),
+            SpectralClass::K => (255, 170, 60),
+            SpectralClass::M => (255, 90, 40),
+        }
+    }
+}
+
+impl fmt::Display for SpectralClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// A main sequence star.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Star {
+    pub class: SpectralClass,
+    /// Numeric subdivision of the spectral class, from 0 (hottest) to 9.
+    pub subclass: u8,
+    /// Mass, in solar masses.
+    pub mass: f64,
+    /// Luminosity, in solar luminosities.
+    pub luminosity: f64,
+    /// Effective temperature, in Kelvin.
+    pub temperature: f64,
+    /// Radius, in solar radii.
+    pub radius: f64,
+}
+
+impl Star {
+    /// Build the star at the given position 'fraction' (0.0 = hottest,
+    /// 1.0 = coolest) inside its spectral class.
+    pub fn from_class(class: SpectralClass, fraction: f64) -> Star {
+        let t = fraction.max(0.0).min(1.0);
+        let (t_hot, t_cool) = class.temperature_range();
+        let (m_heavy, m_light) = class.mass_range();
+
+        let temperature = t_hot + t * (t_cool - t_hot);
+        let mass = (m_heavy.ln() + t * (m_light.ln() - m_heavy.ln())).exp();
+        let luminosity = mass_luminosity(mass);
+        let radius = luminosity.sqrt() * (SOLAR_TEMPERATURE / temperature).powi(2);
+
+        Star {
+            class: class,
+            subclass: ((t * 10.0) as u8).min(9),
+            mass: mass,
+            luminosity: luminosity,
+            temperature: temperature,
+            radius: radius,
+        }
+    }
+
+    /// The (inner, outer) bounds of the habitable zone, in game units, from
+    /// the real distances (see 'solar_flux').
+    pub fn habitable_zone(&self) -> (f64, f64) {
+        let l = self.luminosity.sqrt();
+        (au_to_game_units(0.95 * l), au_to_game_units(1.37 * l))
+    }
+
+    /// Flux received at the given distance from the star, in game units, in
+    /// solar constants.
+    pub fn solar_flux(&self, distance: f64) -> f64 {
+        solar_flux(self.luminosity, distance)
+    }
+
+    /// Equilibrium temperature, in Kelvin, of a body without atmosphere at
+    /// the given distance from the star, in game units. It follows the real
+    /// distance rather than the in-game flux (see 'solar_flux').
+    pub fn equilibrium_temperature(&self, distance: f64) -> f64 {
+        278.6 * self.luminosity.powf(0.25) / game_units_to_au(distance).sqrt()
+    }
+
+    /// The simple in-game representation of the star.
+    pub fn visuals(&self) -> ObjectVisuals {
+        ObjectVisuals::circle(SOLAR_VISUAL_RADIUS * self.radius.powf(0.35),
+                              self.class.color())
+    }
+
+    /// Store the star's characteristics as constants of the given register.
+    pub fn fill_register(&self, register: &mut ObjectRegister) {
+        use core::ObjectPropertyValue::*;
+
+        register.add_constant("spectral_class",
+                              Text(format!("{}{}", self.class, self.subclass)),
+                              "Morgan-Keenan spectral class of the star.");
+        register.add_constant("mass", Float(self.mass), "Mass, in solar masses.");
+        register.add_constant("luminosity",
+                              Float(self.luminosity),
+                              "Luminosity, in solar luminosities.");
+        register.add_constant("temperature",
+                              Float(self.temperature),
+                              "Effective temperature, in Kelvin.");
+
+        register.set_display_name("spectral_class", "Spectral class");
+        register.set_display_name("mass", "Mass");
+        register.set_display_name("luminosity", "Luminosity");
+        register.set_display_name("temperature", "Temperature");
+    }
+}
+
+/// Approximate main sequence mass-luminosity relation, in solar units.
+fn mass_luminosity(mass: f64) -> f64 {
+    if mass < 0.43 {
+        0.23 * mass.powf(2.3)
+    } else if mass < 2.0 {
+        mass.powi(4)
+    } else if mass < 55.0 {
+        1.4 * mass.powf(3.5)
+    } else {
+        32000.0 * mass
+    }
+}
+
+/// Generates main sequence stars, by default with their real-life frequencies.
+pub struct StarGenerator<R: Rng> {
+    class_weights: Vec<(SpectralClass, f64)>,
+    fraction: SamplerFunction<f64, R>,
+}
+
+impl<R: Rng> StarGenerator<R> {
+    builder_setters!(
+        options => {};
+        others => {
+            class_weights { class_weights: Vec<(SpectralClass, f64)> },
+            class_fraction { fraction: SamplerFunction<f64, R> }
+        }
+    );
+}
+
+impl<R: Rng> TypeGenerator<R> for StarGenerator<R> {
+    type Generated = Star;
+
+    fn default() -> StarGenerator<R> {
+        StarGenerator {
+            class_weights: SpectralClass::all()
+                               .iter()
+                               .map(|class| (*class, class.frequency()))
+                               .collect(),
+            fraction: Distribution::Range {
+                          low: 0.0,
+                          high: 1.0,
+                      }
+                      .into(),
+        }
+    }
+
+    fn generate(&mut self, rng: &mut R) -> Result<Star, String> {
+        let class = unwrap_or_err!(weighted_choice(&self.class_weights, rng),
+                                   "StarGenerator : no spectral class with a positive weight");
+        Ok(Star::from_class(class, (self.fraction)(rng)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use super::*;
+    use generator::TypeGenerator;
+
+    #[test]
+    fn test_star_from_class() {
+        let sun = Star::from_class(SpectralClass::G, 0.25);
+        assert_eq!(sun.subclass, 2);
+        assert!((sun.mass - 0.98).abs() < 0.05);
+        assert!((sun.luminosity - 1.0).abs() < 0.2);
+        assert!((sun.radius - 1.0).abs() < 0.2);
+
+        let (inner, outer) = sun.habitable_zone();
+        assert!(inner < 150.0 && 150.0 < outer);
+        assert!((sun.equilibrium_temperature(150.0) - 278.6).abs() < 15.0);
+        assert!((sun.solar_flux(150.0) - sun.luminosity).abs() < 1e-9);
+        assert!((sun.solar_flux(300.0) * 4.0 - sun.luminosity).abs() < 1e-9);
+        assert!(sun.solar_flux(0.0).is_finite());
+
+        let red_dwarf = Star::from_class(SpectralClass::M, 0.5);
+        let blue_giant = Star::from_class(SpectralClass::B, 0.5);
+        assert!(red_dwarf.habitable_zone().1 < sun.habitable_zone().0);
+        assert!(blue_giant.habitable_zone().0 > sun.habitable_zone().1);
+        assert!(red_dwarf.radius < sun.radius && sun.radius < blue_giant.radius);
+
+        // even the dimmest stars leave room for planets outside their surface
+        for &fraction in &[0.0, 1.0] {
+            let dwarf = Star::from_class(SpectralClass::M, fraction);
+            let drawn_radius = SOLAR_VISUAL_RADIUS * dwarf.radius.powf(0.35);
+            assert!(dwarf.habitable_zone().0 > drawn_radius);
+        }
+    }
+
+    #[test]
+    fn test_star_generator_frequencies() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut generator = StarGenerator::default();
+        let n = 100000;
+        let stars = generator.generate_many(&mut rng, n).unwrap();
+
+        let m_stars = stars.iter().filter(|s| s.class == SpectralClass::M).count();
+        let g_stars = stars.iter().filter(|s| s.class == SpectralClass::G).count();
+        assert!((m_stars as f64 / n as f64 - 0.7645).abs() < 0.01);
+        assert!((g_stars as f64 / n as f64 - 0.076).abs() < 0.005);
+
+        let mut generator = StarGenerator::default()
+                                .class_weights(vec![(SpectralClass::A, 1.0)]);
+        let star = generator.generate(&mut rng).unwrap();
+        assert_eq!(star.class, SpectralClass::A);
+        assert!(star.mass >= 1.4 && star.mass <= 2.1);
+    }
+}
@@ -0,0 +1,242 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+use rand::distributions::{Normal, IndependentSample};
+
+/// Maximum number of tries when sampling an altitude from the density profile.
+const MAX_SAMPLING_TRIES: usize = 1000;
+
+/// Radial density of a belt between its inner and outer altitudes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DensityProfile {
+    Uniform,
+    /// Bell-shaped density peaking at the 'peak' fraction of the belt's width,
+    /// with the given standard deviation as a fraction of the belt's width.
+    Gaussian { peak: f64, width: f64 },
+    /// Density varying linearly between its relative values at the inner and
+    /// outer edges.
+    Linear { inner: f64, outer: f64 },
+}
+
+impl DensityProfile {
+    /// Relative density, between 0 and 1, at the given fraction of the belt's
+    /// width.
+    fn density(&self, x: f64) -> f64 {
+        match *self {
+            DensityProfile::Uniform => 1.0,
+            DensityProfile::Gaussian { peak, width } => {
+                (-0.5 * ((x - peak) / width).powi(2)).exp()
+            }
+            DensityProfile::Linear { inner, outer } => {
+                let max = inner.max(outer);
+                if max > 0.0 {
+                    (inner + (outer - inner) * x).max(0.0) / max
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// A depleted ring inside a belt, like the Kirkwood gaps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeltGap {
+    pub altitude: f64,
+    pub width: f64,
+    /// Fraction of the asteroids missing inside the gap (1 for an empty gap).
+    pub depth: f64,
+}
+
+impl BeltGap {
+    /// The gap at the mean-motion resonance where the asteroids complete
+    /// 'asteroid_orbits' revolutions while the planet at the given altitude
+    /// completes 'planet_orbits' (e.g. 3:1 for the main Kirkwood gap).
+    pub fn resonance(planet_altitude: f64,
+                     asteroid_orbits: u32,
+                     planet_orbits: u32,
+                     width: f64,
+                     depth: f64)
+                     -> BeltGap {
+        // Kepler's third law : the altitude goes as the period to the 2/3
+        let period_ratio = planet_orbits as f64 / asteroid_orbits as f64;
+        BeltGap {
+            altitude: planet_altitude * period_ratio.powf(2.0 / 3.0),
+            width: width,
+            depth: depth,
+        }
+    }
+}
+
+/// A clump of asteroids sharing similar orbital elements, e.g. the fragments
+/// of a former larger body.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsteroidFamily {
+    pub altitude: f64,
+    /// Angle of the family, in radians.
+    pub angle: f64,
+    pub altitude_spread: f64,
+    pub angle_spread: f64,
+    /// Fraction of the belt's asteroids belonging to the family.
+    pub fraction: f64,
+}
+
+/// Describes the structure of an asteroid belt and samples the altitude and
+/// angle of its asteroids accordingly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeltProfile {
+    inner_altitude: f64,
+    outer_altitude: f64,
+    density: DensityProfile,
+    gaps: Vec<BeltGap>,
+    families: Vec<AsteroidFamily>,
+    /// Start angle and angular extent of the belt, if it is only partial.
+    arc: Option<(f64, f64)>,
+}
+
+impl BeltProfile {
+    /// A uniform, featureless belt between the given altitudes.
+    pub fn new(inner_altitude: f64, outer_altitude: f64) -> BeltProfile {
+        BeltProfile {
+            inner_altitude: inner_altitude.min(outer_altitude),
+            outer_altitude: inner_altitude.max(outer_altitude),
+            density: DensityProfile::Uniform,
+            gaps: Vec::new(),
+            families: Vec::new(),
+            arc: None,
+        }
+    }
+
+    builder_setters!(options => {}; others => { density { density: DensityProfile } });
+
+    pub fn gap(mut self, gap: BeltGap) -> Self {
+        self.gaps.push(gap);
+        self
+    }
+
+    pub fn family(mut self, family: AsteroidFamily) -> Self {
+        self.families.push(family);
+        self
+    }
+
+    /// Restrict the belt (but not its families) to an arc starting at the
+    /// given angle, in radians.
+    pub fn arc(mut self, start_angle: f64, extent: f64) -> Self {
+        self.arc = Some((start_angle, extent.max(0.0).min(2.0 * PI)));
+        self
+    }
+
+    /// Relative density, between 0 and 1, of the asteroids not belonging to a
+    /// family at the given altitude.
+    pub fn density_at(&self, altitude: f64) -> f64 {
+        if altitude < self.inner_altitude || altitude > self.outer_altitude {
+            return 0.0;
+        }
+        let width = self.outer_altitude - self.inner_altitude;
+        let x = if width > 0.0 { (altitude - self.inner_altitude) / width } else { 0.5 };
+        self.gaps.iter().fold(self.density.density(x), |density, gap| {
+            if (altitude - gap.altitude).abs() < gap.width / 2.0 {
+                density * (1.0 - gap.depth.max(0.0).min(1.0))
+            } else {
+                density
+            }
+        })
+    }
+
+    /// Sample the altitude and angle, in radians, of an asteroid.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> (f64, f64) {
+        let mut choice = rng.next_f64();
+        for family in &self.families {
+            if choice < family.fraction {
+                let altitude = Normal::new(family.altitude, family.altitude_spread.max(0.0));
+                let angle = Normal::new(family.angle, family.angle_spread.max(0.0));
+                return (altitude.ind_sample(rng), angle.ind_sample(rng));
+            }
+            choice -= family.fraction;
+        }
+
+        let (start, extent) = self.arc.unwrap_or((0.0, 2.0 * PI));
+        let angle = start + extent * rng.next_f64();
+        // rejection sampling of the density, which is at most 1
+        let mut altitude = self.inner_altitude;
+        for _ in 0..MAX_SAMPLING_TRIES {
+            altitude = self.inner_altitude +
+                       (self.outer_altitude - self.inner_altitude) * rng.next_f64();
+            if rng.next_f64() < self.density_at(altitude) {
+                break;
+            }
+        }
+        (altitude, angle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+    use rand::{XorShiftRng, SeedableRng};
+    use core::orbital::orbital_period;
+
+    #[test]
+    fn test_belt_density_and_gaps() {
+        let mut rng = XorShiftRng::from_seed([4, 3, 2, 1]);
+        let gap = BeltGap::resonance(100.0, 3, 1, 10.0, 1.0);
+        assert!((orbital_period(1.0, 100.0) / orbital_period(1.0, gap.altitude) - 3.0).abs() <
+                1e-9);
+
+        // density decreasing to zero outwards, with an empty gap
+        let profile = BeltProfile::new(200.0, 400.0)
+                          .density(DensityProfile::Linear {
+                              inner: 1.0,
+                              outer: 0.0,
+                          })
+                          .gap(BeltGap {
+                              altitude: 250.0,
+                              width: 20.0,
+                              depth: 1.0,
+                          });
+        let samples: Vec<(f64, f64)> = (0..20000).map(|_| profile.sample(&mut rng)).collect();
+        assert!(samples.iter().all(|&(altitude, angle)| {
+            altitude >= 200.0 && altitude <= 400.0 && angle >= 0.0 && angle <= 2.0 * PI
+        }));
+        assert!(samples.iter().all(|&(altitude, _)| (altitude - 250.0).abs() >= 10.0));
+        let inner_half = samples.iter().filter(|&&(altitude, _)| altitude < 300.0).count();
+        let outer_half = samples.len() - inner_half;
+        // integrals of the density over the inner half, the gap and the outer half
+        let expected_ratio = (0.375 - 0.075) / 0.125;
+        let ratio = inner_half as f64 / outer_half as f64;
+        assert!((ratio - expected_ratio).abs() < 0.15 * expected_ratio,
+                "inner / outer ratio of {}",
+                ratio);
+
+        let profile = BeltProfile::new(200.0, 400.0).density(DensityProfile::Gaussian {
+            peak: 0.25,
+            width: 0.1,
+        });
+        let mean = (0..20000).map(|_| profile.sample(&mut rng).0).sum::<f64>() / 20000.0;
+        assert!((mean - 250.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn test_belt_families_and_arcs() {
+        let mut rng = XorShiftRng::from_seed([1, 1, 2, 3]);
+        let family = AsteroidFamily {
+            altitude: 300.0,
+            angle: 4.0,
+            altitude_spread: 2.0,
+            angle_spread: 0.05,
+            fraction: 0.3,
+        };
+        let profile = BeltProfile::new(200.0, 400.0)
+                          .arc(PI / 2.0, PI / 4.0)
+                          .family(family.clone());
+        let samples: Vec<(f64, f64)> = (0..10000).map(|_| profile.sample(&mut rng)).collect();
+        let (members, others): (Vec<(f64, f64)>, Vec<(f64, f64)>) =
+            samples.into_iter().partition(|&(_, angle)| (angle - family.angle).abs() < 1.0);
+        assert!(others.iter().all(|&(_, angle)| angle >= PI / 2.0 && angle <= 3.0 * PI / 4.0));
+        assert!((members.len() as f64 / 10000.0 - 0.3).abs() < 0.02);
+        assert!(members.iter().all(|&(altitude, angle)| {
+            (altitude - 300.0).abs() < 5.0 * 2.0 && (angle - 4.0).abs() < 5.0 * 0.05
+        }));
+    }
+}
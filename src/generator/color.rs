@@ -0,0 +1,318 @@
+use rand::Rng;
+
+use super::{TypeGenerator, SamplerFunction, Distribution, weighted_choice};
+
+/// A type-erased generator of RGBA colors, allowing any of the color
+/// generators to be used interchangeably.
+pub type BoxedColorGenerator<R> = Box<TypeGenerator<R, Generated = [f32; 4]>>;
+
+/// Convert a color from the HSV space (hue in degrees, saturation and value
+/// between 0.0 and 1.0) to RGB components between 0.0 and 1.0.
+pub fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (f64, f64, f64) {
+    let h = ((h % 360.0) + 360.0) % 360.0 / 60.0;
+    let c = v * s;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let m = v - c;
+    let (r, g, b) = match h as u8 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    (r + m, g + m, b + m)
+}
+
+/// Convert RGB components between 0.0 and 1.0 to the HSV space (hue in
+/// degrees, saturation and value between 0.0 and 1.0).
+pub fn rgb_to_hsv(r: f64, g: f64, b: f64) -> (f64, f64, f64) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let h = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * (((g - b) / delta) % 6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let s = if max == 0.0 { 0.0 } else { delta / max };
+    ((h + 360.0) % 360.0, s, max)
+}
+
+/// Convert a color from the HSL space (hue in degrees, saturation and
+/// lightness between 0.0 and 1.0) to RGB components between 0.0 and 1.0.
+pub fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (f64, f64, f64) {
+    let v = l + s * l.min(1.0 - l);
+    let s_v = if v == 0.0 { 0.0 } else { 2.0 * (1.0 - l / v) };
+    hsv_to_rgb(h, s_v, v)
+}
+
+/// Convert a generated color to the 8-bit RGB components used by
+/// 'ObjectVisuals'.
+pub fn to_rgb(color: [f32; 4]) -> (u8, u8, u8) {
+    let channel = |c: f32| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+    (channel(color[0]), channel(color[1]), channel(color[2]))
+}
+
+fn clamp_unit(value: f64) -> f64 {
+    value.max(0.0).min(1.0)
+}
+
+/// Generates random RGB colors, with each component having a value between 0.0 and 1.0.
+pub struct ColorGenerator<R: Rng> {
+    pub r: SamplerFunction<f64, R>,
+    pub g: SamplerFunction<f64, R>,
+    pub b: SamplerFunction<f64, R>,
+}
+
+impl<R: Rng> TypeGenerator<R> for ColorGenerator<R> {
+    type Generated = [f32; 4];
+
+    fn default() -> Self {
+        ColorGenerator {
+            r: Distribution::Range {
+                   low: 0.0,
+                   high: 1.0,
+               }
+               .into(),
+            g: Distribution::Range {
+                   low: 0.0,
+                   high: 1.0,
+               }
+               .into(),
+            b: Distribution::Range {
+                   low: 0.0,
+                   high: 1.0,
+               }
+               .into(),
+        }
+    }
+
+    fn generate(&mut self, rng: &mut R) -> Result<Self::Generated, String> {
+        Ok([(self.r)(rng) as f32, (self.g)(rng) as f32, (self.b)(rng) as f32, 1.0])
+    }
+}
+
+/// Generates random colors in the HSV space, with the hue in degrees and the
+/// saturation and value between 0.0 and 1.0.
+pub struct HsvColorGenerator<R: Rng> {
+    pub h: SamplerFunction<f64, R>,
+    pub s: SamplerFunction<f64, R>,
+    pub v: SamplerFunction<f64, R>,
+}
+
+impl<R: Rng> HsvColorGenerator<R> {
+    builder_setters!(
+        options => {};
+        others => {
+            hue { h: SamplerFunction<f64, R> },
+            saturation { s: SamplerFunction<f64, R> },
+            value { v: SamplerFunction<f64, R> }
+        }
+    );
+}
+
+impl<R: Rng> TypeGenerator<R> for HsvColorGenerator<R> {
+    type Generated = [f32; 4];
+
+    fn default() -> Self {
+        HsvColorGenerator {
+            h: Distribution::Range {
+                   low: 0.0,
+                   high: 360.0,
+               }
+               .into(),
+            s: Distribution::Range {
+                   low: 0.4,
+                   high: 0.8,
+               }
+               .into(),
+            v: Distribution::Range {
+                   low: 0.5,
+                   high: 0.9,
+               }
+               .into(),
+        }
+    }
+
+    fn generate(&mut self, rng: &mut R) -> Result<Self::Generated, String> {
+        let (r, g, b) = hsv_to_rgb((self.h)(rng),
+                                   clamp_unit((self.s)(rng)),
+                                   clamp_unit((self.v)(rng)));
+        Ok([r as f32, g as f32, b as f32, 1.0])
+    }
+}
+
+/// Generates random colors in the HSL space, with the hue in degrees and the
+/// saturation and lightness between 0.0 and 1.0.
+pub struct HslColorGenerator<R: Rng> {
+    pub h: SamplerFunction<f64, R>,
+    pub s: SamplerFunction<f64, R>,
+    pub l: SamplerFunction<f64, R>,
+}
+
+impl<R: Rng> HslColorGenerator<R> {
+    builder_setters!(
+        options => {};
+        others => {
+            hue { h: SamplerFunction<f64, R> },
+            saturation { s: SamplerFunction<f64, R> },
+            lightness { l: SamplerFunction<f64, R> }
+        }
+    );
+}
+
+impl<R: Rng> TypeGenerator<R> for HslColorGenerator<R> {
+    type Generated = [f32; 4];
+
+    fn default() -> Self {
+        HslColorGenerator {
+            h: Distribution::Range {
+                   low: 0.0,
+                   high: 360.0,
+               }
+               .into(),
+            s: Distribution::Range {
+                   low: 0.4,
+                   high: 0.8,
+               }
+               .into(),
+            l: Distribution::Range {
+                   low: 0.3,
+                   high: 0.7,
+               }
+               .into(),
+        }
+    }
+
+    fn generate(&mut self, rng: &mut R) -> Result<Self::Generated, String> {
+        let (r, g, b) = hsl_to_rgb((self.h)(rng),
+                                   clamp_unit((self.s)(rng)),
+                                   clamp_unit((self.l)(rng)));
+        Ok([r as f32, g as f32, b as f32, 1.0])
+    }
+}
+
+/// Generates colors picked from a weighted palette, each pick being jittered
+/// in the HSV space for some variety around the palette entries.
+pub struct PaletteColorGenerator<R: Rng> {
+    /// The (RGB color, weight) palette entries.
+    entries: Vec<((u8, u8, u8), f64)>,
+    /// Offset added to the hue of the picked entry, in degrees.
+    hue_jitter: SamplerFunction<f64, R>,
+    /// Offset added to the saturation of the picked entry.
+    saturation_jitter: SamplerFunction<f64, R>,
+    /// Offset added to the value of the picked entry.
+    value_jitter: SamplerFunction<f64, R>,
+}
+
+impl<R: Rng> PaletteColorGenerator<R> {
+    /// Add an entry to the palette, with a weight relative to the others.
+    pub fn entry(mut self, rgb: (u8, u8, u8), weight: f64) -> Self {
+        self.entries.push((rgb, weight));
+        self
+    }
+
+    builder_setters!(
+        options => {};
+        others => {
+            entries { entries: Vec<((u8, u8, u8), f64)> },
+            hue_jitter { hue_jitter: SamplerFunction<f64, R> },
+            saturation_jitter { saturation_jitter: SamplerFunction<f64, R> },
+            value_jitter { value_jitter: SamplerFunction<f64, R> }
+        }
+    );
+}
+
+impl<R: Rng> TypeGenerator<R> for PaletteColorGenerator<R> {
+    type Generated = [f32; 4];
+
+    fn default() -> Self {
+        PaletteColorGenerator {
+            entries: Vec::new(),
+            hue_jitter: Distribution::Constant(0.0).into(),
+            saturation_jitter: Distribution::Constant(0.0).into(),
+            value_jitter: Distribution::Constant(0.0).into(),
+        }
+    }
+
+    fn generate(&mut self, rng: &mut R) -> Result<Self::Generated, String> {
+        let rgb = unwrap_or_err!(
+            weighted_choice(&self.entries, rng),
+            "PaletteColorGenerator : no palette entry with a positive weight");
+
+        let (h, s, v) = rgb_to_hsv(rgb.0 as f64 / 255.0, rgb.1 as f64 / 255.0, rgb.2 as f64 / 255.0);
+        let (r, g, b) = hsv_to_rgb(h + (self.hue_jitter)(rng),
+                                   clamp_unit(s + (self.saturation_jitter)(rng)),
+                                   clamp_unit(v + (self.value_jitter)(rng)));
+        Ok([r as f32, g as f32, b as f32, 1.0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use super::*;
+    use generator::{TypeGenerator, Distribution};
+
+    fn assert_close(a: (f64, f64, f64), b: (f64, f64, f64)) {
+        assert!((a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6 &&
+                (a.2 - b.2).abs() < 1e-6,
+                "{:?} != {:?}",
+                a,
+                b);
+    }
+
+    #[test]
+    fn test_color_space_conversions() {
+        assert_close(hsv_to_rgb(0.0, 1.0, 1.0), (1.0, 0.0, 0.0));
+        assert_close(hsv_to_rgb(120.0, 1.0, 1.0), (0.0, 1.0, 0.0));
+        assert_close(hsv_to_rgb(240.0, 1.0, 0.5), (0.0, 0.0, 0.5));
+        assert_close(hsv_to_rgb(-60.0, 1.0, 1.0), (1.0, 0.0, 1.0));
+        assert_close(hsl_to_rgb(0.0, 1.0, 0.5), (1.0, 0.0, 0.0));
+        assert_close(hsl_to_rgb(200.0, 0.0, 0.25), (0.25, 0.25, 0.25));
+
+        for &(r, g, b) in &[(0.2, 0.4, 0.6), (0.9, 0.1, 0.3), (0.5, 0.5, 0.5)] {
+            let (h, s, v) = rgb_to_hsv(r, g, b);
+            assert_close(hsv_to_rgb(h, s, v), (r, g, b));
+        }
+
+        assert_eq!(to_rgb([1.0, 0.5, 0.0, 1.0]), (255, 128, 0));
+    }
+
+    #[test]
+    fn test_palette_color_generator() {
+        let mut rng = XorShiftRng::from_seed([4, 3, 2, 1]);
+
+        let mut empty = PaletteColorGenerator::default();
+        assert!(empty.generate(&mut rng).is_err());
+
+        let mut palette = PaletteColorGenerator::default()
+                              .entry((255, 0, 0), 3.0)
+                              .entry((0, 0, 255), 1.0);
+        let colors = palette.generate_many(&mut rng, 10000).unwrap();
+        let reds = colors.iter().filter(|c| to_rgb(**c) == (255, 0, 0)).count();
+        let blues = colors.iter().filter(|c| to_rgb(**c) == (0, 0, 255)).count();
+        assert_eq!(reds + blues, colors.len());
+        assert!((reds as f64 / colors.len() as f64 - 0.75).abs() < 0.02);
+
+        let mut jittered = PaletteColorGenerator::default()
+                               .entry((100, 100, 100), 1.0)
+                               .value_jitter(Distribution::Range {
+                                                 low: -0.1,
+                                                 high: 0.1,
+                                             }
+                                             .into());
+        for color in jittered.generate_many(&mut rng, 100).unwrap() {
+            let (h, s, v) = rgb_to_hsv(color[0] as f64, color[1] as f64, color[2] as f64);
+            assert_eq!((h, s), (0.0, 0.0));
+            assert!((v - 100.0 / 255.0).abs() <= 0.1 + 1e-6);
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::f64;
+
+use rand::Rng;
+
+use super::TypeGenerator;
+
+/// Implemented by the generated types which orbit a parent body and can thus
+/// be subjected to 'PlacementConstraints'.
+pub trait Placeable {
+    /// Distance between the center of the parent body and the body's center.
+    fn altitude(&self) -> f64;
+    /// Radius of the body itself.
+    fn radius(&self) -> f64;
+}
+
+/// Radius of the Hill sphere of a body of mass 'mass' orbiting at
+/// 'semi_major_axis' a parent of mass 'parent_mass', i.e. the region where
+/// its own gravity dominates and where its satellites can stably orbit.
+pub fn hill_radius(semi_major_axis: f64, mass: f64, parent_mass: f64) -> f64 {
+    semi_major_axis * (mass / (3.0 * parent_mass)).cbrt()
+}
+
+/// Constraints on the orbital altitude of the bodies generated around a
+/// common parent, enforced by rejection sampling.
+#[derive(Clone, Debug)]
+pub struct PlacementConstraints {
+    /// Minimum altitude of the inner edge of the bodies.
+    min_altitude: f64,
+    /// Maximum altitude of the outer edge of the bodies.
+    max_altitude: f64,
+    /// Minimum radial gap between the edges of a body and of any occupied lane.
+    min_spacing: f64,
+    /// Number of samples drawn before giving up on placing a body.
+    max_attempts: usize,
+    /// If true the placed bodies do not occupy their lanes, which allows
+    /// them to share them (e.g. asteroids inside a belt).
+    shared_lanes: bool,
+    /// The (altitude, half-width) orbital lanes already occupied.
+    occupied: Vec<(f64, f64)>,
+}
+
+impl PlacementConstraints {
+    pub fn new() -> PlacementConstraints {
+        PlacementConstraints {
+            min_altitude: 0.0,
+            max_altitude: f64::INFINITY,
+            min_spacing: 0.0,
+            max_attempts: 100,
+            shared_lanes: false,
+            occupied: Vec::new(),
+        }
+    }
+
+    builder_setters!(
+        options => {};
+        others => {
+            min_altitude { min_altitude: f64 },
+            max_altitude { max_altitude: f64 },
+            min_spacing { min_spacing: f64 },
+            max_attempts { max_attempts: usize },
+            shared_lanes { shared_lanes: bool }
+        }
+    );
+
+    /// Keep the placed bodies clear of the parent's surface.
+    pub fn outside_parent(mut self, parent_radius: f64) -> Self {
+        self.min_altitude = self.min_altitude.max(parent_radius);
+        self
+    }
+
+    /// Keep the placed bodies inside the Hill sphere of their parent, which
+    /// itself orbits its own parent at 'parent_altitude'.
+    pub fn within_hill_sphere(mut self,
+                              parent_altitude: f64,
+                              parent_mass: f64,
+                              grandparent_mass: f64)
+                              -> Self {
+        let limit = hill_radius(parent_altitude, parent_mass, grandparent_mass);
+        self.max_altitude = self.max_altitude.min(limit);
+        self
+    }
+
+    /// Mark the orbital lane of half-width 'radius' at 'altitude' as occupied.
+    pub fn occupy(&mut self, altitude: f64, radius: f64) {
+        self.occupied.push((altitude, radius));
+    }
+
+    /// Check if a body of the given radius can be placed at 'altitude',
+    /// describing the violated constraint otherwise.
+    pub fn check(&self, altitude: f64, radius: f64) -> Result<(), String> {
+        if !(radius > 0.0) {
+            return Err(format!("non-positive radius {:.2}", radius));
+        }
+        if altitude - radius < self.min_altitude {
+            return Err(format!("altitude {:.2} with radius {:.2} is below the minimum altitude \
+                                {:.2}",
+                               altitude,
+                               radius,
+                               self.min_altitude));
+        }
+        if altitude + radius > self.max_altitude {
+            return Err(format!("altitude {:.2} with radius {:.2} is above the maximum altitude \
+                                {:.2}",
+                               altitude,
+                               radius,
+                               self.max_altitude));
+        }
+        for &(lane_altitude, lane_radius) in &self.occupied {
+            let gap = (altitude - lane_altitude).abs() - radius - lane_radius;
+            if gap < self.min_spacing {
+                return Err(format!("altitude {:.2} with radius {:.2} is too close to the \
+                                    occupied lane at {:.2}",
+                                   altitude,
+                                   radius,
+                                   lane_altitude));
+            }
+        }
+        Ok(())
+    }
+
+    /// Repeatedly sample an altitude until it satisfies the constraints for a
+    /// body of the given radius, occupying the corresponding lane unless
+    /// lanes are shared.
+    pub fn sample_altitude<R, F>(&mut self,
+                                 rng: &mut R,
+                                 mut sampler: F,
+                                 radius: f64)
+                                 -> Result<f64, String>
+        where R: Rng,
+              F: FnMut(&mut R) -> f64
+    {
+        let mut last_rejection = String::new();
+        for _ in 0..self.max_attempts {
+            let altitude = sampler(rng);
+            match self.check(altitude, radius) {
+                Ok(()) => {
+                    self.place(altitude, radius);
+                    return Ok(altitude);
+                }
+                Err(why) => last_rejection = why,
+            }
+        }
+        Err(self.failure(last_rejection))
+    }
+
+    /// Repeatedly generate a body until it satisfies the constraints,
+    /// occupying the corresponding lane unless lanes are shared.
+    pub fn generate<R, G>(&mut self, generator: &mut G, rng: &mut R) -> Result<G::Generated, String>
+        where R: Rng,
+              G: TypeGenerator<R>,
+              G::Generated: Placeable
+    {
+        let mut last_rejection = String::new();
+        for _ in 0..self.max_attempts {
+            let body = try!(generator.generate(rng));
+            let (altitude, radius) = (body.altitude(), body.radius());
+            match self.check(altitude, radius) {
+                Ok(()) => {
+                    self.place(altitude, radius);
+                    return Ok(body);
+                }
+                Err(why) => last_rejection = why,
+            }
+        }
+        Err(self.failure(last_rejection))
+    }
+
+    /// Generate 'n' bodies satisfying the constraints.
+    pub fn generate_many<R, G>(&mut self,
+                               generator: &mut G,
+                               rng: &mut R,
+                               n: usize)
+                               -> Result<Vec<G::Generated>, String>
+        where R: Rng,
+              G: TypeGenerator<R>,
+              G::Generated: Placeable
+    {
+        let mut generated = Vec::with_capacity(n);
+        for _ in 0..n {
+            generated.push(try!(self.generate(generator, rng)));
+        }
+        Ok(generated)
+    }
+
+    fn place(&mut self, altitude: f64, radius: f64) {
+        if !self.shared_lanes {
+            self.occupy(altitude, radius);
+        }
+    }
+
+    fn failure(&self, last_rejection: String) -> String {
+        format!("PlacementConstraints : no valid placement found after {} attempts (altitude \
+                 range [{:.2}, {:.2}], minimum spacing {:.2}, {} occupied lanes) ; last \
+                 rejection : {}",
+                self.max_attempts,
+                self.min_altitude,
+                self.max_altitude,
+                self.min_spacing,
+                self.occupied.len(),
+                last_rejection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use super::*;
+    use generator::Distribution;
+
+    #[test]
+    fn test_placement_constraints() {
+        let mut rng = XorShiftRng::from_seed([7, 7, 7, 7]);
+        let sampler = Distribution::Range {
+                          low: 0.0,
+                          high: 100.0,
+                      }
+                      .to_sampler();
+
+        let mut constraints = PlacementConstraints::new()
+                                  .outside_parent(20.0)
+                                  .max_altitude(90.0)
+                                  .min_spacing(5.0);
+        let mut altitudes = Vec::new();
+        for _ in 0..4 {
+            altitudes.push(constraints.sample_altitude(&mut rng, &sampler, 2.0).unwrap());
+        }
+        for (i, a) in altitudes.iter().enumerate() {
+            assert!(*a >= 22.0 && *a <= 88.0);
+            for b in &altitudes[i + 1..] {
+                assert!((a - b).abs() >= 9.0);
+            }
+        }
+
+        assert!(constraints.check(50.0, -1.0).is_err());
+        let mut crowded = PlacementConstraints::new()
+                              .outside_parent(20.0)
+                              .max_altitude(30.0)
+                              .max_attempts(50);
+        crowded.sample_altitude(&mut rng, |_: &mut XorShiftRng| 25.0, 5.0).unwrap();
+        let error = crowded.sample_altitude(&mut rng, &sampler, 5.0).unwrap_err();
+        assert!(error.contains("after 50 attempts"));
+    }
+
+    #[test]
+    fn test_hill_sphere() {
+        assert!((hill_radius(1.0, 3.0e-6, 1.0) - 0.01).abs() < 1e-6);
+
+        let constraints = PlacementConstraints::new().within_hill_sphere(100.0, 0.375, 1.0);
+        assert!(constraints.check(45.0, 4.0).is_ok());
+        assert!(constraints.check(48.0, 4.0).is_err());
+    }
+}
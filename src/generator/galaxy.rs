@@ -0,0 +1,304 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use util::{Vector, Vector2f};
+use super::TypeGenerator;
+
+const NAME_SYLLABLES: [&'static str; 24] = ["sol", "ar", "ven", "tau", "ri", "cen", "zo", "lyr",
+                                            "ka", "mir", "o", "dra", "nex", "pho", "bel", "an",
+                                            "us", "ix", "e", "ra", "thu", "gal", "ne", "vos"];
+
+/// A star system of the galaxy map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SystemNode {
+    pub name: String,
+    /// Position of the system in the galaxy, in galaxy units.
+    pub position: Vector2f,
+    /// Seed from which the system's content is to be generated.
+    pub seed: u32,
+}
+
+/// A graph of star systems linked by hyperlanes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GalaxyMap {
+    pub systems: Vec<SystemNode>,
+    /// The (from, to) indices of the systems linked by a hyperlane, with
+    /// 'from < to'.
+    pub hyperlanes: Vec<(usize, usize)>,
+}
+
+impl GalaxyMap {
+    /// Indices of the systems directly reachable from the given one.
+    pub fn neighbors(&self, system: usize) -> Vec<usize> {
+        self.hyperlanes
+            .iter()
+            .filter_map(|&(a, b)| {
+                if a == system {
+                    Some(b)
+                } else if b == system {
+                    Some(a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Return true if every system can be reached from any other.
+    pub fn is_connected(&self) -> bool {
+        if self.systems.is_empty() {
+            return true;
+        }
+        let mut visited = vec![false; self.systems.len()];
+        let mut stack = vec![0];
+        visited[0] = true;
+        while let Some(system) = stack.pop() {
+            for neighbor in self.neighbors(system) {
+                if !visited[neighbor] {
+                    visited[neighbor] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        visited.iter().all(|v| *v)
+    }
+
+    /// Index of the system closest to the given position, if there is any
+    /// closer than 'max_distance'.
+    pub fn system_at(&self, position: Vector2f, max_distance: f64) -> Option<usize> {
+        let mut closest = None;
+        let mut closest_distance = max_distance;
+        for (i, system) in self.systems.iter().enumerate() {
+            let distance = system.position.minus(&position).norm();
+            if distance <= closest_distance {
+                closest = Some(i);
+                closest_distance = distance;
+            }
+        }
+        closest
+    }
+
+    fn lane_length(&self, a: usize, b: usize) -> f64 {
+        self.systems[a].position.minus(&self.systems[b].position).norm()
+    }
+
+    fn has_lane(&self, a: usize, b: usize) -> bool {
+        let lane = (a.min(b), a.max(b));
+        self.hyperlanes.iter().any(|l| *l == lane)
+    }
+
+    /// Return true if the (a, b) lane would cross an existing one.
+    fn crosses_lane(&self, a: usize, b: usize) -> bool {
+        let (p1, p2) = (self.systems[a].position, self.systems[b].position);
+        self.hyperlanes.iter().any(|&(c, d)| {
+            if c == a || c == b || d == a || d == b {
+                return false;
+            }
+            segments_intersect(p1, p2, self.systems[c].position, self.systems[d].position)
+        })
+    }
+}
+
+fn segments_intersect(p1: Vector2f, p2: Vector2f, q1: Vector2f, q2: Vector2f) -> bool {
+    let cross = |o: Vector2f, a: Vector2f, b: Vector2f| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let (d1, d2) = (cross(q1, q2, p1), cross(q1, q2, p2));
+    let (d3, d4) = (cross(p1, p2, q1), cross(p1, p2, q2));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Generates a galaxy map : star systems scattered across a disc and linked
+/// by hyperlanes, with every system being reachable.
+pub struct GalaxyGenerator {
+    systems_number: usize,
+    /// Radius of the galaxy's disc, in galaxy units.
+    radius: f64,
+    /// Minimum distance between two systems.
+    min_distance: f64,
+    /// Number of additional lanes each system tries to have with its
+    /// closest neighbors, on top of the ones ensuring connectivity.
+    extra_lanes: usize,
+    /// Maximum length of the additional lanes.
+    max_lane_length: f64,
+    /// Number of position samples drawn before giving up on placing a system.
+    max_attempts: usize,
+}
+
+impl GalaxyGenerator {
+    pub fn new() -> GalaxyGenerator {
+        GalaxyGenerator {
+            systems_number: 30,
+            radius: 1000.0,
+            min_distance: 120.0,
+            extra_lanes: 2,
+            max_lane_length: 350.0,
+            max_attempts: 1000,
+        }
+    }
+
+    builder_setters!(
+        options => {};
+        others => {
+            systems_number { systems_number: usize },
+            radius { radius: f64 },
+            min_distance { min_distance: f64 },
+            extra_lanes { extra_lanes: usize },
+            max_lane_length { max_lane_length: f64 },
+            max_attempts { max_attempts: usize }
+        }
+    );
+
+    fn generate_name<R: Rng>(rng: &mut R) -> String {
+        let syllables = rng.gen_range(2, 4);
+        let name: String = (0..syllables)
+                               .map(|_| NAME_SYLLABLES[rng.gen_range(0, NAME_SYLLABLES.len())])
+                               .collect();
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => name,
+        }
+    }
+}
+
+impl<R: Rng> TypeGenerator<R> for GalaxyGenerator {
+    type Generated = GalaxyMap;
+
+    fn default() -> GalaxyGenerator {
+        GalaxyGenerator::new()
+    }
+
+    fn generate(&mut self, rng: &mut R) -> Result<GalaxyMap, String> {
+        let mut map = GalaxyMap {
+            systems: Vec::with_capacity(self.systems_number),
+            hyperlanes: Vec::new(),
+        };
+
+        while map.systems.len() < self.systems_number {
+            let mut position = None;
+            for _ in 0..self.max_attempts {
+                let r = self.radius * rng.gen::<f64>().sqrt();
+                let theta = 2.0 * PI * rng.gen::<f64>();
+                let candidate = (r * theta.cos(), r * theta.sin());
+                if map.systems
+                      .iter()
+                      .all(|s| s.position.minus(&candidate).norm() >= self.min_distance) {
+                    position = Some(candidate);
+                    break;
+                }
+            }
+            let position = unwrap_or_err!(position,
+                                          format!("GalaxyGenerator : could not place system #{} \
+                                                   at least {} away from the others after {} \
+                                                   attempts",
+                                                  map.systems.len() + 1,
+                                                  self.min_distance,
+                                                  self.max_attempts));
+
+            let mut name = GalaxyGenerator::generate_name(rng);
+            while map.systems.iter().any(|s| s.name == name) {
+                name = GalaxyGenerator::generate_name(rng);
+            }
+            map.systems.push(SystemNode {
+                name: name,
+                position: position,
+                seed: rng.next_u32(),
+            });
+        }
+
+        // minimum spanning tree (Prim) ensuring connectivity
+        let n = map.systems.len();
+        let mut in_tree = vec![false; n];
+        let mut best: Vec<(f64, usize)> = vec![(::std::f64::INFINITY, 0); n];
+        if n > 0 {
+            best[0].0 = 0.0;
+        }
+        for _ in 0..n {
+            let next = (0..n)
+                           .filter(|i| !in_tree[*i])
+                           .fold(None, |acc: Option<usize>, i| {
+                               match acc {
+                                   Some(j) if best[j].0 <= best[i].0 => Some(j),
+                                   _ => Some(i),
+                               }
+                           })
+                           .unwrap();
+            in_tree[next] = true;
+            if next != 0 {
+                let parent = best[next].1;
+                map.hyperlanes.push((parent.min(next), parent.max(next)));
+            }
+            for i in 0..n {
+                let distance = map.lane_length(next, i);
+                if !in_tree[i] && distance < best[i].0 {
+                    best[i] = (distance, next);
+                }
+            }
+        }
+
+        // additional non-crossing lanes between close neighbors
+        for a in 0..n {
+            let mut candidates: Vec<usize> = (0..n).filter(|b| *b != a).collect();
+            candidates.sort_by(|x, y| {
+                map.lane_length(a, *x).partial_cmp(&map.lane_length(a, *y)).unwrap()
+            });
+            for b in candidates.into_iter().take(self.extra_lanes) {
+                if map.lane_length(a, b) <= self.max_lane_length && !map.has_lane(a, b) &&
+                   !map.crosses_lane(a, b) {
+                    map.hyperlanes.push((a.min(b), a.max(b)));
+                }
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use super::*;
+    use generator::TypeGenerator;
+    use util::Vector;
+
+    #[test]
+    fn test_galaxy_generation() {
+        let mut rng = XorShiftRng::from_seed([42, 42, 42, 42]);
+        let mut generator = GalaxyGenerator::new();
+        let map = generator.generate(&mut rng).unwrap();
+
+        assert_eq!(map.systems.len(), 30);
+        assert!(map.is_connected());
+        for (i, a) in map.systems.iter().enumerate() {
+            assert!(a.position.norm() <= 1000.0);
+            for b in &map.systems[i + 1..] {
+                assert!(a.position.minus(&b.position).norm() >= 120.0);
+                assert!(a.name != b.name);
+            }
+        }
+        for (i, lane) in map.hyperlanes.iter().enumerate() {
+            assert!(lane.0 < lane.1);
+            assert!(!map.hyperlanes[i + 1..].contains(lane));
+        }
+        assert!(map.hyperlanes.len() >= map.systems.len() - 1);
+
+        let first = map.systems[0].position;
+        assert_eq!(map.system_at((first.0 + 1.0, first.1), 5.0), Some(0));
+
+        let mut same_rng = XorShiftRng::from_seed([42, 42, 42, 42]);
+        assert_eq!(generator.generate(&mut same_rng).unwrap(), map);
+    }
+
+    #[test]
+    fn test_galaxy_generation_failure() {
+        let mut rng = XorShiftRng::from_seed([1, 1, 1, 1]);
+        let mut generator = GalaxyGenerator::new()
+                                .systems_number(50)
+                                .radius(100.0)
+                                .max_attempts(20);
+        assert!(generator.generate(&mut rng).is_err());
+    }
+}
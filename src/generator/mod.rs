@@ -1,13 +1,28 @@
+mod belt;
+mod color;
+mod constraints;
+mod galaxy;
+mod noise;
+mod surface;
+
 use rand::Rng;
 use rand::distributions::{Normal, Range, IndependentSample};
 
+pub use self::belt::{BeltProfile, BeltGap, AsteroidFamily, DensityProfile};
+pub use self::color::{BoxedColorGenerator, ColorGenerator, HsvColorGenerator, HslColorGenerator,
+                      PaletteColorGenerator, hsv_to_rgb, rgb_to_hsv, hsl_to_rgb, to_rgb};
+pub use self::galaxy::{GalaxyGenerator, GalaxyMap, SystemNode};
+pub use self::constraints::{Placeable, PlacementConstraints, hill_radius};
+pub use self::noise::PerlinNoise;
+pub use self::surface::{PlanetTextureGenerator, SurfaceImage, SurfaceType};
+
 pub type SamplerFunction<T, R> = Box<Fn(&mut R) -> T>;
 
 /// Randomly generate instances of a certain type.
 pub trait TypeGenerator<R: Rng> {
     type Generated;
 
-    fn default() -> Self;
+    fn default() -> Self where Self: Sized;
 
     fn generate(&mut self, rng: &mut R) -> Result<Self::Generated, String>;
 
@@ -56,44 +71,42 @@ impl<R: Rng> Into<SamplerFunction<f64, R>> for Distribution {
     }
 }
 
-/// Generates random RGB colors, with each component having a value between 0.0 and 1.0.
-pub struct ColorGenerator<R: Rng> {
-    pub r: SamplerFunction<f64, R>,
-    pub g: SamplerFunction<f64, R>,
-    pub b: SamplerFunction<f64, R>,
-}
-
-impl<R: Rng> TypeGenerator<R> for ColorGenerator<R> {
-    type Generated = [f32; 4];
-
-    fn default() -> Self {
-        ColorGenerator {
-            r: Distribution::Range {
-                   low: 0.0,
-                   high: 1.0,
-               }
-               .into(),
-            g: Distribution::Range {
-                   low: 0.0,
-                   high: 1.0,
-               }
-               .into(),
-            b: Distribution::Range {
-                   low: 0.0,
-                   high: 1.0,
-               }
-               .into(),
-        }
+/// Randomly pick one of the choices, with a probability proportional to its
+/// weight. Return None if no choice has a positive weight.
+pub fn weighted_choice<T: Copy, R: Rng>(choices: &[(T, f64)], rng: &mut R) -> Option<T> {
+    let total = choices.iter().fold(0.0, |sum, &(_, weight)| sum + weight);
+    if choices.is_empty() || total <= 0.0 {
+        return None;
     }
 
-    fn generate(&mut self, rng: &mut R) -> Result<Self::Generated, String> {
-        Ok([(self.r)(rng) as f32, (self.g)(rng) as f32, (self.b)(rng) as f32, 1.0])
+    let mut pick = Range::new(0.0, total).ind_sample(rng);
+    for &(choice, weight) in choices {
+        if pick < weight {
+            return Some(choice);
+        }
+        pick -= weight;
     }
+    Some(choices[choices.len() - 1].0)
 }
 
-#[macro_export]
-macro_rules! generator_sample {
-    ($self_: ident, $sampler_opt_fn: ident, $rng: ident) => (
-        ($self_.$sampler_opt_fn.as_ref().unwrap())($rng)
-    )
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use super::*;
+
+    #[test]
+    fn test_weighted_choice() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        assert_eq!(weighted_choice::<u8, _>(&[], &mut rng), None);
+        assert_eq!(weighted_choice(&[('a', 0.0)], &mut rng), None);
+        assert_eq!(weighted_choice(&[('a', 0.0), ('b', 2.0)], &mut rng), Some('b'));
+
+        let n = 10000;
+        let a_count = (0..n)
+                          .filter(|_| weighted_choice(&[('a', 1.0), ('b', 3.0)], &mut rng) ==
+                                      Some('a'))
+                          .count();
+        assert!((a_count as f64 / n as f64 - 0.25).abs() < 0.02);
+    }
 }
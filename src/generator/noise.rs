@@ -0,0 +1,75 @@
+use rand::Rng;
+
+/// Two-dimensional gradient noise (Ken Perlin's improved noise), seeded by a
+/// random permutation table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerlinNoise {
+    /// Shuffled values of 0..256, repeated twice to avoid wrapping indices.
+    permutation: Vec<u8>,
+}
+
+impl PerlinNoise {
+    pub fn new<R: Rng>(rng: &mut R) -> PerlinNoise {
+        let mut table: Vec<u8> = (0..256).map(|i| i as u8).collect();
+        rng.shuffle(&mut table);
+        let permutation = table.iter().chain(table.iter()).cloned().collect();
+        PerlinNoise { permutation: permutation }
+    }
+
+    /// Sample the noise at the given point, in [-1, 1].
+    /// The noise is zero on the integer lattice, with features roughly one
+    /// unit wide.
+    pub fn noise(&self, x: f64, y: f64) -> f64 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (xi, yi) = ((x0 as i64 & 255) as usize, (y0 as i64 & 255) as usize);
+        let (x, y) = (x - x0, y - y0);
+        let (u, v) = (fade(x), fade(y));
+
+        let p = &self.permutation;
+        let (a, b) = (p[xi] as usize + yi, p[xi + 1] as usize + yi);
+        let value = lerp(v,
+                         lerp(u, grad(p[a], x, y), grad(p[b], x - 1.0, y)),
+                         lerp(u, grad(p[a + 1], x, y - 1.0), grad(p[b + 1], x - 1.0, y - 1.0)));
+        value.max(-1.0).min(1.0)
+    }
+
+    /// Fractal Brownian motion : sum of 'octaves' layers of noise, each with
+    /// its frequency multiplied by 'lacunarity' and its amplitude by
+    /// 'persistence'. The result is normalized back into [-1, 1].
+    pub fn fractal(&self, x: f64, y: f64, octaves: usize, lacunarity: f64, persistence: f64) -> f64 {
+        let (mut sum, mut total_amplitude) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        for _ in 0..octaves {
+            sum += amplitude * self.noise(x * frequency, y * frequency);
+            total_amplitude += amplitude;
+            frequency *= lacunarity;
+            amplitude *= persistence;
+        }
+        if total_amplitude > 0.0 {
+            sum / total_amplitude
+        } else {
+            0.0
+        }
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+fn grad(hash: u8, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
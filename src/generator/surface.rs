@@ -0,0 +1,252 @@
+use rand::Rng;
+
+use super::{TypeGenerator, SamplerFunction, Distribution};
+use super::noise::PerlinNoise;
+
+/// The broad kinds of planetary surfaces, driving the biomes' coloring.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurfaceType {
+    /// Bare rock, with seas only where it is warm enough.
+    Rocky,
+    /// Mostly covered by water, with vegetation on temperate lands.
+    Oceanic,
+    /// Frozen crust over dark ice plains.
+    Icy,
+    /// Dark basalt cut by lava seas.
+    Volcanic,
+    /// Cloud bands of a gas giant.
+    Gaseous,
+}
+
+impl SurfaceType {
+    /// Elevation below which the surface is covered by a liquid (or its ice).
+    fn sea_level(&self) -> f64 {
+        match *self {
+            SurfaceType::Rocky => -0.25,
+            SurfaceType::Oceanic => 0.05,
+            SurfaceType::Icy => -0.1,
+            SurfaceType::Volcanic => -0.2,
+            SurfaceType::Gaseous => -1.0,
+        }
+    }
+
+    /// Color of a point of the surface, from its elevation in [-1, 1], its
+    /// latitude in [-1, 1] and its local temperature in Kelvin.
+    fn biome_color(&self, elevation: f64, latitude: f64, temperature: f64) -> [f64; 3] {
+        if *self == SurfaceType::Gaseous {
+            let band = (0.5 + 0.5 * (latitude * 9.0 + elevation * 3.0).sin()).powi(2);
+            return if temperature < 200.0 {
+                mix([90.0, 140.0, 200.0], [180.0, 215.0, 235.0], band)
+            } else {
+                mix([170.0, 110.0, 60.0], [230.0, 205.0, 160.0], band)
+            };
+        }
+
+        let sea_level = self.sea_level();
+        if elevation < sea_level {
+            let depth = ((sea_level - elevation) / (sea_level + 1.0)).min(1.0);
+            return if *self == SurfaceType::Volcanic {
+                mix([250.0, 140.0, 30.0], [200.0, 40.0, 10.0], depth)
+            } else if *self == SurfaceType::Icy || temperature < 271.0 {
+                mix([215.0, 230.0, 240.0], [150.0, 175.0, 200.0], depth)
+            } else {
+                mix([45.0, 100.0, 175.0], [15.0, 30.0, 100.0], depth)
+            };
+        }
+
+        let height = ((elevation - sea_level) / (1.0 - sea_level)).min(1.0);
+        match *self {
+            SurfaceType::Volcanic => mix([55.0, 45.0, 45.0], [115.0, 100.0, 95.0], height),
+            SurfaceType::Icy => mix([170.0, 185.0, 200.0], [245.0, 250.0, 255.0], height),
+            _ if temperature < 255.0 => mix([225.0, 230.0, 240.0], [250.0, 250.0, 255.0], height),
+            _ if temperature > 330.0 => mix([215.0, 180.0, 115.0], [160.0, 110.0, 70.0], height),
+            SurfaceType::Oceanic if temperature > 265.0 => {
+                if height < 0.55 {
+                    mix([70.0, 140.0, 60.0], [40.0, 95.0, 45.0], height / 0.55)
+                } else {
+                    mix([120.0, 105.0, 85.0], [240.0, 240.0, 245.0], (height - 0.55) / 0.45)
+                }
+            }
+            _ => mix([150.0, 125.0, 100.0], [185.0, 175.0, 165.0], height),
+        }
+    }
+}
+
+fn mix(a: [f64; 3], b: [f64; 3], t: f64) -> [f64; 3] {
+    let t = t.max(0.0).min(1.0);
+    [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1]), a[2] + t * (b[2] - a[2])]
+}
+
+/// A CPU-side image, stored as rows of 4 8-bit RGBA channels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SurfaceImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl SurfaceImage {
+    /// Create a fully transparent image.
+    pub fn new(width: u32, height: u32) -> SurfaceImage {
+        SurfaceImage {
+            width: width,
+            height: height,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        let i = ((y * self.width + x) * 4) as usize;
+        self.pixels[i..i + 4].copy_from_slice(&rgba);
+    }
+}
+
+/// Generates the disc image of a planet's surface from fractal noise, the
+/// noise being used as an elevation map colored according to the surface
+/// type and to the local temperature.
+/// The same random number generator state always yields the same image.
+pub struct PlanetTextureGenerator<R: Rng> {
+    /// Width and height of the generated image, in pixels.
+    size: u32,
+    surface_type: SurfaceType,
+    /// Mean surface temperature, in Kelvin. The poles and the heights are
+    /// colder.
+    temperature: SamplerFunction<f64, R>,
+    /// Number of noise layers, each adding finer details.
+    octaves: usize,
+    /// Number of noise features across the planet's diameter.
+    frequency: f64,
+    /// Amplitude ratio between two successive noise layers.
+    persistence: f64,
+}
+
+impl<R: Rng> PlanetTextureGenerator<R> {
+    builder_setters!(
+        options => {};
+        others => {
+            size { size: u32 },
+            surface_type { surface_type: SurfaceType },
+            temperature { temperature: SamplerFunction<f64, R> },
+            octaves { octaves: usize },
+            frequency { frequency: f64 },
+            persistence { persistence: f64 }
+        }
+    );
+}
+
+impl<R: Rng> TypeGenerator<R> for PlanetTextureGenerator<R> {
+    type Generated = SurfaceImage;
+
+    fn default() -> PlanetTextureGenerator<R> {
+        PlanetTextureGenerator {
+            size: 64,
+            surface_type: SurfaceType::Rocky,
+            temperature: Distribution::Constant(288.0).into(),
+            octaves: 5,
+            frequency: 3.0,
+            persistence: 0.5,
+        }
+    }
+
+    fn generate(&mut self, rng: &mut R) -> Result<SurfaceImage, String> {
+        if self.size == 0 {
+            return Err("PlanetTextureGenerator : the image size must be positive".into());
+        }
+        let noise = PerlinNoise::new(rng);
+        let temperature = (self.temperature)(rng);
+
+        let mut image = SurfaceImage::new(self.size, self.size);
+        let half_size = self.size as f64 / 2.0;
+        for y in 0..self.size {
+            for x in 0..self.size {
+                // coordinates inside the planet's disc, in [-1, 1]
+                let dx = (x as f64 + 0.5 - half_size) / half_size;
+                let dy = (y as f64 + 0.5 - half_size) / half_size;
+                let r2 = dx * dx + dy * dy;
+                if r2 > 1.0 {
+                    continue;
+                }
+
+                let elevation = noise.fractal(dx * self.frequency,
+                                              dy * self.frequency,
+                                              self.octaves,
+                                              2.0,
+                                              self.persistence);
+                let local_temperature = temperature - 45.0 * dy * dy -
+                                        40.0 * elevation.max(0.0);
+                let color = self.surface_type.biome_color(elevation, dy, local_temperature);
+                // limb darkening, for a spherical look
+                let shade = 0.6 + 0.4 * (1.0 - r2).sqrt();
+                image.set_pixel(x,
+                                y,
+                                [(color[0] * shade) as u8,
+                                 (color[1] * shade) as u8,
+                                 (color[2] * shade) as u8,
+                                 255]);
+            }
+        }
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, XorShiftRng};
+
+    use super::*;
+    use generator::{TypeGenerator, PerlinNoise};
+
+    #[test]
+    fn test_perlin_noise() {
+        let noise = PerlinNoise::new(&mut XorShiftRng::from_seed([5, 6, 7, 8]));
+        assert_eq!(noise.noise(3.0, 7.0), 0.0);
+        let (mut min, mut max) = (0.0f64, 0.0f64);
+        for i in 0..2000 {
+            let (x, y) = (i as f64 * 0.137, i as f64 * 0.071);
+            let value = noise.fractal(x, y, 4, 2.0, 0.5);
+            assert!(value >= -1.0 && value <= 1.0);
+            assert!((noise.noise(x, y) - noise.noise(x + 1e-6, y)).abs() < 1e-4);
+            min = min.min(value);
+            max = max.max(value);
+        }
+        assert!(min < -0.2 && max > 0.2);
+    }
+
+    #[test]
+    fn test_planet_texture_generation() {
+        let generate = |seed: u32, surface_type: SurfaceType| {
+            PlanetTextureGenerator::default()
+                .size(32)
+                .surface_type(surface_type)
+                .generate(&mut XorShiftRng::from_seed([seed, 2, 3, 4]))
+                .unwrap()
+        };
+        let image = generate(1, SurfaceType::Oceanic);
+        assert_eq!(image.pixels.len(), 32 * 32 * 4);
+        assert_eq!(image, generate(1, SurfaceType::Oceanic));
+        assert!(image != generate(2, SurfaceType::Oceanic));
+        assert!(image != generate(1, SurfaceType::Volcanic));
+
+        // transparent outside of the planet's disc
+        assert_eq!(image.pixel(0, 0), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(31, 31), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(16, 16)[3], 255);
+
+        // icy surfaces are brighter than volcanic ones
+        let brightness = |image: &SurfaceImage| {
+            image.pixels.chunks(4).fold(0u64, |sum, p| sum + p[0] as u64 + p[1] as u64 + p[2] as u64)
+        };
+        assert!(brightness(&generate(1, SurfaceType::Icy)) >
+                brightness(&generate(1, SurfaceType::Volcanic)));
+
+        assert!(PlanetTextureGenerator::default()
+                    .size(0)
+                    .generate(&mut XorShiftRng::from_seed([1, 2, 3, 4]))
+                    .is_err());
+    }
+}
@@ -1,5 +1,5 @@
-pub mod core;
-#[macro_use] pub mod generator;
 #[macro_use] pub mod util;
+#[macro_use] pub mod generator;
+pub mod core;
 
 extern crate rand;
@@ -0,0 +1,62 @@
+/// Order the nodes of a forest so that each node comes after its parent,
+/// given the index of the parent of each node (if any).
+/// The nodes of the same depth keep their relative order.
+///
+/// Return an error naming a node which is its own ancestor, if any.
+pub fn parent_first_order(parents: &[Option<usize>]) -> Result<Vec<usize>, String> {
+    let n = parents.len();
+    let mut depths: Vec<Option<usize>> = vec![None; n];
+
+    for start in 0..n {
+        // walk up until a node of known depth or a root
+        let mut path = Vec::new();
+        let mut current = Some(start);
+        let mut base_depth = 0;
+        while let Some(node) = current {
+            if node >= n {
+                return Err(format!("hierarchy : node #{} has an unknown parent #{}",
+                                   path.last().cloned().unwrap_or(start),
+                                   node));
+            }
+            if let Some(depth) = depths[node] {
+                base_depth = depth + 1;
+                break;
+            }
+            if path.contains(&node) {
+                return Err(format!("hierarchy : node #{} is its own ancestor", node));
+            }
+            path.push(node);
+            current = parents[node];
+        }
+        for (i, node) in path.iter().rev().enumerate() {
+            depths[*node] = Some(base_depth + i);
+        }
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|node| depths[*node]);
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parent_first_order() {
+        // 0 <- 2 <- 1, 3 <- 4
+        let parents = [None, Some(2), Some(0), None, Some(3)];
+        let order = parent_first_order(&parents).unwrap();
+        assert_eq!(order, vec![0, 3, 2, 4, 1]);
+        for (position, node) in order.iter().enumerate() {
+            if let Some(parent) = parents[*node] {
+                assert!(order[..position].contains(&parent));
+            }
+        }
+
+        assert_eq!(parent_first_order(&[]).unwrap(), vec![]);
+        assert!(parent_first_order(&[Some(0)]).is_err());
+        assert!(parent_first_order(&[None, Some(2), Some(3), Some(1)]).is_err());
+        assert!(parent_first_order(&[Some(5)]).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
 pub type Vector2f = (f64, f64);
 
 pub trait Vector<T> {
@@ -20,3 +22,302 @@ impl Vector<f64> for Vector2f {
         (self.0.powi(2) + self.1.powi(2)).sqrt()
     }
 }
+
+/// A 2D vector with the usual operations, convertible from and to 'Vector2f'.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vec2 {
+    pub fn new(x: f64, y: f64) -> Vec2 {
+        Vec2 { x: x, y: y }
+    }
+
+    pub fn zero() -> Vec2 {
+        Vec2::new(0.0, 0.0)
+    }
+
+    /// Unit vector of the given angle, in radians.
+    pub fn from_angle(angle: f64) -> Vec2 {
+        Vec2::new(angle.cos(), angle.sin())
+    }
+
+    pub fn dot(self, other: Vec2) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Z component of the 3D cross product : positive if 'other' is
+    /// counterclockwise from this vector.
+    pub fn cross(self, other: Vec2) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length(self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    pub fn length_squared(self) -> f64 {
+        self.dot(self)
+    }
+
+    /// The unit vector of the same direction, or the zero vector.
+    pub fn normalize(self) -> Vec2 {
+        let length = self.length();
+        if length > 0.0 {
+            self / length
+        } else {
+            Vec2::zero()
+        }
+    }
+
+    /// Rotate the vector counterclockwise by the given angle, in radians.
+    pub fn rotate(self, angle: f64) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    /// The vector rotated counterclockwise by a quarter turn.
+    pub fn perpendicular(self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+
+    /// Angle of the vector from the x axis, in radians between -π and π.
+    pub fn angle(self) -> f64 {
+        self.y.atan2(self.x)
+    }
+
+    /// Signed angle from this vector to the other, in radians between -π and π.
+    pub fn angle_to(self, other: Vec2) -> f64 {
+        self.cross(other).atan2(self.dot(other))
+    }
+
+    /// Linear interpolation between this vector (t = 0) and the other (t = 1).
+    pub fn lerp(self, other: Vec2, t: f64) -> Vec2 {
+        self + (other - self) * t
+    }
+
+    pub fn distance(self, other: Vec2) -> f64 {
+        (other - self).length()
+    }
+}
+
+impl From<Vector2f> for Vec2 {
+    fn from(v: Vector2f) -> Vec2 {
+        Vec2::new(v.0, v.1)
+    }
+}
+
+impl From<Vec2> for Vector2f {
+    fn from(v: Vec2) -> Vector2f {
+        (v.x, v.y)
+    }
+}
+
+impl Add for Vec2 {
+    type Output = Vec2;
+    fn add(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+    fn sub(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+    fn neg(self) -> Vec2 {
+        Vec2::new(-self.x, -self.y)
+    }
+}
+
+impl Mul<f64> for Vec2 {
+    type Output = Vec2;
+    fn mul(self, k: f64) -> Vec2 {
+        Vec2::new(self.x * k, self.y * k)
+    }
+}
+
+impl Mul<Vec2> for f64 {
+    type Output = Vec2;
+    fn mul(self, v: Vec2) -> Vec2 {
+        v * self
+    }
+}
+
+impl Div<f64> for Vec2 {
+    type Output = Vec2;
+    fn div(self, k: f64) -> Vec2 {
+        Vec2::new(self.x / k, self.y / k)
+    }
+}
+
+impl AddAssign for Vec2 {
+    fn add_assign(&mut self, other: Vec2) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vec2 {
+    fn sub_assign(&mut self, other: Vec2) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<f64> for Vec2 {
+    fn mul_assign(&mut self, k: f64) {
+        *self = *self * k;
+    }
+}
+
+/// A 2D affine transform, as the first two rows of its 3x3 matrix.
+/// Transforms compose like the matrices : 'a * b' applies 'b' first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform2 {
+    pub matrix: [[f64; 3]; 2],
+}
+
+impl Transform2 {
+    pub fn identity() -> Transform2 {
+        Transform2 { matrix: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]] }
+    }
+
+    pub fn translation(offset: Vec2) -> Transform2 {
+        Transform2 { matrix: [[1.0, 0.0, offset.x], [0.0, 1.0, offset.y]] }
+    }
+
+    /// Counterclockwise rotation around the origin, in radians.
+    pub fn rotation(angle: f64) -> Transform2 {
+        let (sin, cos) = angle.sin_cos();
+        Transform2 { matrix: [[cos, -sin, 0.0], [sin, cos, 0.0]] }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Transform2 {
+        Transform2 { matrix: [[sx, 0.0, 0.0], [0.0, sy, 0.0]] }
+    }
+
+    /// This transform followed by the other one.
+    pub fn then(&self, other: &Transform2) -> Transform2 {
+        *other * *self
+    }
+
+    /// Transform a point.
+    pub fn apply(&self, point: Vec2) -> Vec2 {
+        let m = &self.matrix;
+        Vec2::new(m[0][0] * point.x + m[0][1] * point.y + m[0][2],
+                  m[1][0] * point.x + m[1][1] * point.y + m[1][2])
+    }
+
+    /// Transform a vector, i.e. ignoring the translation.
+    pub fn apply_vector(&self, vector: Vec2) -> Vec2 {
+        let m = &self.matrix;
+        Vec2::new(m[0][0] * vector.x + m[0][1] * vector.y,
+                  m[1][0] * vector.x + m[1][1] * vector.y)
+    }
+
+    /// The inverse transform, if the transform is not degenerate.
+    pub fn inverse(&self) -> Option<Transform2> {
+        let m = &self.matrix;
+        let determinant = m[0][0] * m[1][1] - m[0][1] * m[1][0];
+        if determinant.abs() < ::std::f64::EPSILON {
+            return None;
+        }
+        let (a, b, c, d) = (m[1][1] / determinant,
+                            -m[0][1] / determinant,
+                            -m[1][0] / determinant,
+                            m[0][0] / determinant);
+        Some(Transform2 {
+            matrix: [[a, b, -(a * m[0][2] + b * m[1][2])],
+                     [c, d, -(c * m[0][2] + d * m[1][2])]],
+        })
+    }
+}
+
+impl Mul for Transform2 {
+    type Output = Transform2;
+    fn mul(self, other: Transform2) -> Transform2 {
+        let (a, b) = (&self.matrix, &other.matrix);
+        let mut matrix = [[0.0; 3]; 2];
+        for i in 0..2 {
+            for j in 0..3 {
+                matrix[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j];
+            }
+            matrix[i][2] += a[i][2];
+        }
+        Transform2 { matrix: matrix }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use super::*;
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_vec2() {
+        let (u, v) = (Vec2::new(3.0, 4.0), Vec2::new(-2.0, 1.0));
+        assert_eq!(u + v, Vec2::new(1.0, 5.0));
+        assert_eq!(u - v, Vec2::new(5.0, 3.0));
+        assert_eq!(-u, Vec2::new(-3.0, -4.0));
+        assert_eq!(u * 2.0, 2.0 * u);
+        assert_eq!(u / 2.0, Vec2::new(1.5, 2.0));
+        let mut w = u;
+        w += v;
+        w -= u;
+        w *= 3.0;
+        assert_eq!(w, v * 3.0);
+
+        assert_eq!(u.dot(v), -2.0);
+        assert_eq!(u.cross(v), 11.0);
+        assert_eq!(v.cross(u), -11.0);
+        assert_eq!((u.length(), u.length_squared()), (5.0, 25.0));
+        assert_eq!(u.normalize(), Vec2::new(0.6, 0.8));
+        assert_eq!(Vec2::zero().normalize(), Vec2::zero());
+        assert_eq!(u.distance(Vec2::new(0.0, 0.0)), 5.0);
+        assert_eq!(u.lerp(v, 0.5), Vec2::new(0.5, 2.5));
+
+        assert_close(Vec2::new(1.0, 0.0).rotate(PI / 2.0), Vec2::new(0.0, 1.0));
+        assert_close(u.rotate(0.3).rotate(-0.3), u);
+        assert_eq!(u.perpendicular(), Vec2::new(-4.0, 3.0));
+        assert_close(Vec2::from_angle(u.angle()) * u.length(), u);
+        assert!((Vec2::new(1.0, 0.0).angle_to(Vec2::new(0.0, -2.0)) + PI / 2.0).abs() < 1e-12);
+
+        let tuple: Vector2f = u.into();
+        assert_eq!(tuple, (3.0, 4.0));
+        assert_eq!(Vec2::from(tuple), u);
+    }
+
+    #[test]
+    fn test_transform2() {
+        let p = Vec2::new(2.0, 1.0);
+        assert_eq!(Transform2::identity().apply(p), p);
+        assert_eq!(Transform2::translation(Vec2::new(1.0, -1.0)).apply(p),
+                   Vec2::new(3.0, 0.0));
+        assert_eq!(Transform2::scale(2.0, 3.0).apply(p), Vec2::new(4.0, 3.0));
+        assert_close(Transform2::rotation(PI / 2.0).apply(p), Vec2::new(-1.0, 2.0));
+
+        // scale, then rotate, then translate
+        let transform = Transform2::scale(2.0, 2.0)
+                            .then(&Transform2::rotation(PI))
+                            .then(&Transform2::translation(Vec2::new(10.0, 0.0)));
+        assert_close(transform.apply(p), Vec2::new(6.0, -2.0));
+        assert_close(transform.apply_vector(p), Vec2::new(-4.0, -2.0));
+        let composed = Transform2::translation(Vec2::new(10.0, 0.0)) * Transform2::rotation(PI) *
+                       Transform2::scale(2.0, 2.0);
+        assert_close(composed.apply(p), transform.apply(p));
+
+        let inverse = transform.inverse().unwrap();
+        assert_close(inverse.apply(transform.apply(p)), p);
+        assert_close((transform * inverse).apply(p), p);
+        assert!(Transform2::scale(1.0, 0.0).inverse().is_none());
+    }
+}
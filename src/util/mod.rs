@@ -1,6 +1,10 @@
+mod hierarchy;
 mod math;
+mod spatial;
 
-pub use self::math::{Vector, Vector2f};
+pub use self::hierarchy::parent_first_order;
+pub use self::math::{Vector, Vector2f, Vec2, Transform2};
+pub use self::spatial::{SpatialIndex, SpatialEntry};
 
 /// Generate the builder setter methods for any parameter.
 #[macro_export]
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use util::Vector2f;
+
+/// An entry of a 'SpatialIndex' : a disc carrying a value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpatialEntry<T> {
+    pub position: Vector2f,
+    pub radius: f64,
+    pub value: T,
+}
+
+/// Uniform grid bucketing discs by the cells they overlap, answering the
+/// point, radius and rectangle queries without going through all the entries.
+/// Moving entries are to be indexed anew ('clear' then 'insert') after each
+/// update.
+pub struct SpatialIndex<T> {
+    cell_size: f64,
+    cells: HashMap<(i64, i64), Vec<usize>>,
+    entries: Vec<SpatialEntry<T>>,
+}
+
+impl<T> SpatialIndex<T> {
+    /// Create an empty index whose grid cells have the given size, ideally
+    /// around the size of the entries and of the usual queries.
+    pub fn new(cell_size: f64) -> Self {
+        assert!(cell_size > 0.0);
+        SpatialIndex {
+            cell_size: cell_size,
+            cells: HashMap::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[SpatialEntry<T>] {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.entries.clear();
+    }
+
+    /// Index the disc of the given center and radius.
+    pub fn insert(&mut self, position: Vector2f, radius: f64, value: T) {
+        let index = self.entries.len();
+        let radius = radius.max(0.0);
+        let (min, max) = (self.cell(position.0 - radius, position.1 - radius),
+                          self.cell(position.0 + radius, position.1 + radius));
+        for x in min.0..max.0 + 1 {
+            for y in min.1..max.1 + 1 {
+                self.cells.entry((x, y)).or_insert_with(Vec::new).push(index);
+            }
+        }
+        self.entries.push(SpatialEntry {
+            position: position,
+            radius: radius,
+            value: value,
+        });
+    }
+
+    /// The entries whose disc contains the point.
+    pub fn at_point(&self, point: Vector2f) -> Vec<&SpatialEntry<T>> {
+        self.within_radius(point, 0.0)
+    }
+
+    /// The entries whose disc intersects the circle of the given center and
+    /// radius.
+    pub fn within_radius(&self, center: Vector2f, radius: f64) -> Vec<&SpatialEntry<T>> {
+        self.candidates((center.0 - radius, center.1 - radius),
+                        (center.0 + radius, center.1 + radius))
+            .into_iter()
+            .map(|i| &self.entries[i])
+            .filter(|entry| {
+                let (dx, dy) = (entry.position.0 - center.0, entry.position.1 - center.1);
+                (dx * dx + dy * dy).sqrt() <= radius + entry.radius
+            })
+            .collect()
+    }
+
+    /// The entries whose disc intersects the rectangle of the given opposite
+    /// corners.
+    pub fn within_rect(&self, min: Vector2f, max: Vector2f) -> Vec<&SpatialEntry<T>> {
+        let (min, max) = ((min.0.min(max.0), min.1.min(max.1)),
+                          (min.0.max(max.0), min.1.max(max.1)));
+        self.candidates(min, max)
+            .into_iter()
+            .map(|i| &self.entries[i])
+            .filter(|entry| {
+                // distance to the closest point of the rectangle
+                let (x, y) = entry.position;
+                let (dx, dy) = (x - x.max(min.0).min(max.0), y - y.max(min.1).min(max.1));
+                (dx * dx + dy * dy).sqrt() <= entry.radius
+            })
+            .collect()
+    }
+
+    /// The entry within 'max_distance' of the point (counting from the edge of
+    /// its disc) whose center is the closest to the point, if any.
+    pub fn nearest(&self, point: Vector2f, max_distance: f64) -> Option<&SpatialEntry<T>> {
+        let distance = |entry: &SpatialEntry<T>| {
+            (entry.position.0 - point.0).hypot(entry.position.1 - point.1)
+        };
+        self.within_radius(point, max_distance)
+            .into_iter()
+            .fold(None, |closest, entry| match closest {
+                Some(closest) if distance(closest) <= distance(entry) => Some(closest),
+                _ => Some(entry),
+            })
+    }
+
+    fn cell(&self, x: f64, y: f64) -> (i64, i64) {
+        ((x / self.cell_size).floor() as i64, (y / self.cell_size).floor() as i64)
+    }
+
+    /// Indices of the entries overlapping the cells covered by the given
+    /// rectangle, without duplicates.
+    fn candidates(&self, min: Vector2f, max: Vector2f) -> Vec<usize> {
+        let (min, max) = (self.cell(min.0, min.1), self.cell(max.0, max.1));
+        let cells_number = (max.0 - min.0 + 1) as f64 * (max.1 - min.1 + 1) as f64;
+        if cells_number > self.cells.len() as f64 {
+            // going through the occupied cells is cheaper
+            let mut candidates: Vec<usize> = self.cells
+                                                 .iter()
+                                                 .filter(|&(cell, _)| {
+                                                     cell.0 >= min.0 && cell.0 <= max.0 &&
+                                                     cell.1 >= min.1 &&
+                                                     cell.1 <= max.1
+                                                 })
+                                                 .flat_map(|(_, indices)| indices.iter().cloned())
+                                                 .collect();
+            candidates.sort();
+            candidates.dedup();
+            return candidates;
+        }
+
+        let mut candidates = Vec::new();
+        for x in min.0..max.0 + 1 {
+            for y in min.1..max.1 + 1 {
+                if let Some(indices) = self.cells.get(&(x, y)) {
+                    candidates.extend_from_slice(indices);
+                }
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, XorShiftRng, SeedableRng};
+
+    #[test]
+    fn test_spatial_index_queries() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        let mut index = SpatialIndex::new(25.0);
+        for i in 0..2000 {
+            index.insert((rng.gen_range(-500.0, 500.0), rng.gen_range(-500.0, 500.0)),
+                         rng.gen_range(0.0, 40.0),
+                         i);
+        }
+        index.insert((1000.0, 1000.0), 5.0, 2000);
+        assert_eq!(index.len(), 2001);
+
+        // same results as going through all the entries
+        let sorted = |entries: Vec<&SpatialEntry<usize>>| {
+            let mut values: Vec<usize> = entries.iter().map(|entry| entry.value).collect();
+            values.sort();
+            values
+        };
+        for _ in 0..50 {
+            let center = (rng.gen_range(-600.0, 600.0), rng.gen_range(-600.0, 600.0));
+            let radius = rng.gen_range(0.0, 150.0);
+            let expected = index.entries()
+                                .iter()
+                                .filter(|entry| {
+                                    (entry.position.0 - center.0)
+                                        .hypot(entry.position.1 - center.1) <=
+                                    radius + entry.radius
+                                })
+                                .map(|entry| entry.value)
+                                .collect::<Vec<_>>();
+            assert_eq!(sorted(index.within_radius(center, radius)), expected);
+
+            let corner = (center.0 + radius, center.1 - radius / 2.0);
+            let expected = index.entries()
+                                .iter()
+                                .filter(|entry| {
+                                    let (x, y) = entry.position;
+                                    let dx = x - x.max(center.0).min(corner.0);
+                                    let dy = y - y.max(corner.1).min(center.1);
+                                    dx.hypot(dy) <= entry.radius
+                                })
+                                .map(|entry| entry.value)
+                                .collect::<Vec<_>>();
+            assert_eq!(sorted(index.within_rect(center, corner)), expected);
+        }
+        assert_eq!(sorted(index.within_rect((-1e6, -1e6), (1e6, 1e6))).len(), 2001);
+
+        assert_eq!(sorted(index.at_point((1003.0, 1000.0))), vec![2000]);
+        assert_eq!(index.nearest((1010.0, 1000.0), 10.0).unwrap().value, 2000);
+        assert!(index.nearest((1010.0, 1000.0), 4.0).is_none());
+        index.clear();
+        assert!(index.is_empty() && index.at_point((1000.0, 1000.0)).is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+extern crate rand;
+extern crate solar_rustlib;
+#[macro_use]
+extern crate solar_rust_derive;
+
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use solar_rustlib::generator::{Distribution, SamplerFunction, TypeGenerator};
+
+#[derive(Debug, PartialEq)]
+struct Crater {
+    depth: f64,
+    width: f64,
+    slope: f64,
+    rim: Option<f64>,
+    label: String,
+}
+
+#[derive(TypeGenerator)]
+#[generator(generated = "Crater", finish = "assemble")]
+struct CraterGenerator<R: 'static + Rng> {
+    depth: Option<SamplerFunction<f64, R>>,
+    #[generator(default = "Distribution::Constant(10.0)")]
+    width: Option<SamplerFunction<f64, R>>,
+    slope: Option<SamplerFunction<f64, R>>,
+    #[generator(setter = "rim_height", optional)]
+    rim: Option<SamplerFunction<f64, R>>,
+    #[generator(default = "\"crater\".to_string()")]
+    label: String,
+}
+
+impl<R: 'static + Rng> CraterGenerator<R> {
+    fn assemble(&mut self, _: &mut R, samples: CraterGeneratorSamples) -> Result<Crater, String> {
+        Ok(Crater {
+            depth: samples.depth,
+            width: samples.width,
+            slope: samples.slope,
+            rim: samples.rim,
+            label: self.label.clone(),
+        })
+    }
+}
+
+#[test]
+fn test_derived_generator() {
+    let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+    // the missing required samplers are reported in their declaration order
+    let mut generator = CraterGenerator::<XorShiftRng>::default();
+    assert_eq!(generator.generate(&mut rng),
+               Err("CraterGenerator : unspecified depth distribution".to_string()));
+    let mut generator = generator.depth(Distribution::Constant(2.0).to_sampler());
+    assert_eq!(generator.generate(&mut rng),
+               Err("CraterGenerator : unspecified slope distribution".to_string()));
+
+    // the defaults apply, and the optional samplers may be left unspecified
+    let mut generator = generator.slope(Distribution::Constant(0.5).to_sampler());
+    assert_eq!(generator.generate(&mut rng),
+               Ok(Crater {
+                   depth: 2.0,
+                   width: 10.0,
+                   slope: 0.5,
+                   rim: None,
+                   label: "crater".to_string(),
+               }));
+
+    let mut generator = generator.width(Distribution::Constant(4.0).to_sampler())
+                                 .rim_height(Distribution::Range {
+                                                 low: 1.0,
+                                                 high: 3.0,
+                                             }
+                                             .to_sampler())
+                                 .label("basin".to_string());
+    let crater = generator.generate(&mut rng).unwrap();
+    assert_eq!(crater.width, 4.0);
+    assert!(crater.rim.map_or(false, |rim| rim >= 1.0 && rim < 3.0));
+    assert_eq!(crater.label, "basin");
+}